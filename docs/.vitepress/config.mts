@@ -31,6 +31,7 @@ export default defineConfig({
           { text: "Inteiros e Reais", link: "/numerico" },
           { text: "Texto e Concatenação", link: "/texto" },
           { text: "Booleano e Condicionais", link: "/booleano" },
+          { text: "Listas", link: "/lista" },
         ],
       },
       {
@@ -42,6 +43,10 @@ export default defineConfig({
           { text: "Laço Enquanto", link: "/enquanto" },
         ],
       },
+      {
+        text: "Funções",
+        link: "/funcoes",
+      },
       {
         text: "Exemplos Avançados",
         base: "/exemplos",
@@ -3,10 +3,11 @@ use std::io::{Read, Result, Write};
 use std::str;
 use wasm_bindgen::prelude::*;
 
-use lina::compiler::compile;
+use lina::compiler::{compile, CompileError};
+use lina::diagnostic::Diagnostic;
 use lina::lexer::{lex, LexicalError};
 use lina::parser::{parse, SyntaxError};
-use lina::vm::{LinaVm, RuntimeError, VmState};
+use lina::vm::{LinaVm, VmError, VmState};
 
 #[wasm_bindgen]
 extern "C" {
@@ -38,6 +39,7 @@ impl Write for Output {
 #[wasm_bindgen]
 struct Terminal {
     vm: LinaVm<Input, Output>,
+    code: String, // source of the running program, shown in its errors
 }
 
 #[wasm_bindgen]
@@ -47,6 +49,7 @@ impl Terminal {
     pub fn new() -> Self {
         Self {
             vm: LinaVm::empty(Input(VecDeque::new()), Output()),
+            code: String::new(),
         }
     }
 
@@ -55,23 +58,36 @@ impl Terminal {
         _ = self.vm.stdin.0.write(input.as_bytes());
     }
 
+    fn report(&mut self, diagnostic: Diagnostic) {
+        _ = writeln!(
+            self.vm.stdout,
+            "{}",
+            diagnostic.render("main.lina", &self.code)
+        );
+    }
+
     fn lex_err(&mut self, err: LexicalError) {
-        _ = writeln!(self.vm.stdout, "Erro Léxico: {}", err.msg);
-        _ = writeln!(self.vm.stdout, "main.lina:{}:{}", err.row, err.col);
+        self.report(Diagnostic::from(&err));
+    }
+
+    fn syn_err(&mut self, errors: Vec<SyntaxError>) {
+        for err in errors.iter() {
+            self.report(Diagnostic::from(err));
+        }
     }
 
-    fn syn_err(&mut self, err: SyntaxError) {
-        _ = writeln!(self.vm.stdout, "Erro Sintático: {}", err.msg);
-        _ = writeln!(self.vm.stdout, "main.lina:{}:{}", err.pos.row, err.pos.col);
+    fn com_err(&mut self, err: CompileError) {
+        self.report(Diagnostic::from(&err));
     }
 
-    fn run_err(&mut self, err: RuntimeError) {
-        _ = writeln!(self.vm.stdout, "Erro: {err}");
+    fn run_err(&mut self, err: VmError) {
+        self.report(Diagnostic::from(&err));
     }
 
     #[allow(dead_code)]
     pub fn start(&mut self, code: &str) -> String {
         self.vm.stdin.0.clear();
+        self.code = code.to_string();
 
         let tkns = match lex(code) {
             Ok(tkns) => tkns,
@@ -83,12 +99,21 @@ impl Terminal {
         let prgm = match parse(tkns) {
             Ok(prgm) => prgm,
             Err(err) => {
-                self.syn_err(err);
+                self.syn_err(err.errors);
                 return Default::default();
             }
         };
-        let byco = compile(&prgm);
-        self.vm.start(byco);
+        let byco = match compile(&prgm) {
+            Ok(byco) => byco,
+            Err(err) => {
+                self.com_err(err);
+                return Default::default();
+            }
+        };
+        if let Err(err) = self.vm.start(byco) {
+            _ = writeln!(self.vm.stdout, "Erro: bytecode inválido: {err}");
+            return Default::default();
+        }
 
         self.resume(100)
     }
@@ -104,7 +129,7 @@ impl Terminal {
                     }
                 }
                 Ok(s @ VmState::WillRead) => {
-                    if self.vm.stdin.0.len() > 0 {
+                    if !self.vm.stdin.0.is_empty() {
                         count += 1;
                     } else {
                         break s.to_string();
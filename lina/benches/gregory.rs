@@ -0,0 +1,138 @@
+//! Runs `examples/gregory.txt` with fewer terms, so the time is spent in the
+//! dispatch loop of the vm rather than in compiling or printing. The compiled
+//! code is run as is and with every operand widened to four bytes, the size
+//! operands had before the compact encoding, to show what it gains.
+//!
+//! ```console
+//! cargo bench -p lina --bench gregory
+//! ```
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use common::build;
+use lina::compiler::ByteCode;
+use lina::vm::{LinaVm, OpCode, WIDE_SIZE};
+
+const TERMS: u64 = 2_000_000;
+const RUNS: u32 = 5;
+
+fn is_jump(op: OpCode) -> bool {
+    matches!(op, OpCode::Jmp | OpCode::JmpT | OpCode::JmpF)
+}
+
+/// `code` with a WIDE prefix before every instruction that has operands
+fn widen(code: &ByteCode) -> ByteCode {
+    let bytes = &code.bytecode;
+
+    // jump targets and call addresses are kept as offsets of the old code
+    let mut instrs = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let start = offset;
+        let mut op = OpCode::try_from(bytes[offset]).expect("code should be valid");
+        offset += 1;
+        let wide = op == OpCode::Wide;
+        if wide {
+            op = OpCode::try_from(bytes[offset]).expect("code should be valid");
+            offset += 1;
+        }
+
+        let mut args = Vec::new();
+        for &size in op.operands() {
+            let size = if wide { WIDE_SIZE } else { size };
+            let mut value = [0; 8];
+            value[..size].copy_from_slice(&bytes[offset..offset + size]);
+            offset += size;
+
+            let mut arg = i64::from_le_bytes(value);
+            if is_jump(op) {
+                let shift = 64 - 8 * size as u32;
+                arg = ((arg << shift) >> shift) + offset as i64;
+            }
+            args.push(arg);
+        }
+        instrs.push((start, op, args));
+    }
+
+    let mut moved = HashMap::new();
+    let mut len = 0;
+    for (start, _, args) in &instrs {
+        moved.insert(*start as i64, len as i64);
+        len += match args.len() {
+            0 => 1,
+            n => 2 + n * WIDE_SIZE,
+        };
+    }
+    moved.insert(bytes.len() as i64, len as i64);
+
+    let mut bytecode = Vec::with_capacity(len);
+    for (_, op, args) in instrs {
+        if args.is_empty() {
+            bytecode.push(op as u8);
+            continue;
+        }
+
+        bytecode.extend([OpCode::Wide as u8, op as u8]);
+        let end = (bytecode.len() + args.len() * WIDE_SIZE) as i64;
+        for (i, arg) in args.into_iter().enumerate() {
+            let arg = match op {
+                op if is_jump(op) => moved[&arg] - end,
+                OpCode::Call if i == 0 => moved[&arg],
+                _ => arg,
+            };
+            bytecode.extend(&arg.to_le_bytes()[..WIDE_SIZE]);
+        }
+    }
+
+    ByteCode {
+        bytecode,
+        constants: code.constants.clone(),
+        lines: Vec::new(),
+    }
+}
+
+/// the best and the mean time of running `code`
+fn time(code: &ByteCode) -> (Duration, Duration) {
+    let mut times = Vec::new();
+    for _ in 0..RUNS {
+        let copy = ByteCode {
+            bytecode: code.bytecode.clone(),
+            constants: code.constants.clone(),
+            lines: code.lines.clone(),
+        };
+        let mut vm = LinaVm::new(copy, &[][..], Vec::new()).expect("code should be valid");
+        let start = Instant::now();
+        vm.run().unwrap_or_else(|err| panic!("{}", err.err));
+        times.push(start.elapsed());
+        assert!(vm.stdout.starts_with(b"3.14159"));
+    }
+
+    let best = times.iter().min().copied().unwrap_or_default();
+    (best, times.iter().sum::<Duration>() / RUNS)
+}
+
+fn main() {
+    let code = include_str!("../../examples/gregory.txt").replace("1000000000", &TERMS.to_string());
+    let compact = build(&code);
+    let wide = widen(&compact);
+
+    println!("gregory: {TERMS} termos");
+    let mut bests = Vec::new();
+    for (name, code) in [("compacto", &compact), ("largo", &wide)] {
+        let (best, mean) = time(code);
+        println!(
+            "  {name:8} {:3} bytes, melhor: {best:?}, média: {mean:?}, {:.1} ns por termo",
+            code.bytecode.len(),
+            best.as_nanos() as f64 / TERMS as f64
+        );
+        bests.push(best);
+    }
+    println!(
+        "  o código compacto leva {:.0}% do tempo do largo",
+        100.0 * bests[0].as_secs_f64() / bests[1].as_secs_f64()
+    );
+}
@@ -1,22 +1,73 @@
 use std::collections::HashMap;
 
 use crate::syntax::{Block, Expression, Program, SyntaxTree, Type};
-use crate::token::{Literal, Operador};
-use crate::vm::{LinaValue, OpCode};
+use crate::token::{Literal, Operador, TokenPos};
+use crate::vm::{LinaValue, OpCode, ADDR_SIZE, JUMP_SIZE, MAX_SLOTS, WIDE_SIZE};
 
+#[derive(Debug)]
+pub struct CompileError {
+    pub pos: TokenPos,
+    pub msg: String,
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Erro de compilação: {}", self.msg)?;
+        writeln!(f, "Posição -> {}:{}", self.pos.row, self.pos.col)
+    }
+}
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+#[derive(Debug)]
 pub struct ByteCode {
     pub bytecode: Vec<u8>,
     pub constants: Vec<LinaValue>,
+    pub lines: Vec<(usize, TokenPos)>, // offset where the code of each statement starts
 }
 
 type VarTable<'a> = HashMap<&'a str, usize>;
 
+fn addr_bytes(addr: usize) -> [u8; WIDE_SIZE] {
+    u32::try_from(addr)
+        .expect("operand should fit in 32 bits")
+        .to_le_bytes()
+}
+
+fn offs_bytes(offset: isize) -> [u8; WIDE_SIZE] {
+    i32::try_from(offset)
+        .expect("jump should fit in 32 bits")
+        .to_le_bytes()
+}
+
+/// jumps out of a loop, waiting for the loop to be fully compiled
+#[derive(Debug, Default)]
+struct Loop {
+    breaks: Vec<usize>,    // pare: jump to after the loop
+    continues: Vec<usize>, // continue: jump to the next iteration
+}
+
 #[derive(Debug)]
 pub struct Compiler<'a> {
     bytecode: Vec<u8>,
     constants: Vec<LinaValue>,
     scopes: Vec<VarTable<'a>>,
-    vi: usize,
+    vi: usize,                              // slot of the next variable declared
+    slots: usize,                           // slots the current frame needs
+    functions: HashMap<&'a str, usize>,     // entry address of each function
+    pending: Vec<&'a SyntaxTree<'a>>,       // functions waiting to be placed after the program
+    calls: Vec<(usize, &'a str, TokenPos)>, // call operands waiting for the function address
+    loops: Vec<Loop>,                       // loops enclosing the current instruction
+    pos: TokenPos,                          // start of the statement being compiled
+    lines: Vec<(usize, TokenPos)>,          // line table, see `ByteCode::lines`
+    wide_jumps: bool,                       // forward jumps are emitted after a WIDE prefix
+    long_jump: bool,                        // a narrow forward jump could not reach its target
+}
+
+impl Default for Compiler<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> Compiler<'a> {
@@ -26,34 +77,168 @@ impl<'a> Compiler<'a> {
             constants: Vec::new(),
             scopes: vec![HashMap::new()],
             vi: 0,
+            slots: 0,
+            functions: HashMap::new(),
+            pending: Vec::new(),
+            calls: Vec::new(),
+            loops: Vec::new(),
+            pos: TokenPos::default(),
+            lines: Vec::new(),
+            wide_jumps: false,
+            long_jump: false,
+        }
+    }
+
+    /// emits `op` with its operands in the narrow form, or after a WIDE prefix
+    /// when one of them does not fit, returning where the operands start
+    fn op_args(&mut self, op: OpCode, args: &[usize]) -> usize {
+        let sizes = op.operands();
+        let narrow = sizes
+            .iter()
+            .zip(args)
+            .all(|(&size, &arg)| size >= WIDE_SIZE || arg >> (8 * size) == 0);
+
+        if !narrow {
+            self.op(OpCode::Wide);
+        }
+        self.op(op);
+
+        let pos = self.bytecode.len();
+        for (&size, &arg) in sizes.iter().zip(args) {
+            let size = if narrow { size } else { WIDE_SIZE };
+            self.bytecode.extend(&addr_bytes(arg)[..size]); // little-endian, so the low bytes
         }
+        pos
     }
 
     fn op_const(&mut self, addr: usize) {
-        self.bytecode.push(OpCode::Const as u8);
-        self.bytecode.extend(usize::to_ne_bytes(addr));
+        self.op_args(OpCode::Const, &[addr]);
     }
 
     fn op_store(&mut self, addr: usize) {
-        self.bytecode.push(OpCode::Store as u8);
-        self.bytecode.extend(usize::to_ne_bytes(addr));
+        self.op_args(OpCode::Store, &[addr]);
     }
 
     fn op_load(&mut self, addr: usize) {
-        self.bytecode.push(OpCode::Load as u8);
-        self.bytecode.extend(usize::to_ne_bytes(addr));
+        self.op_args(OpCode::Load, &[addr]);
+    }
+
+    /// emits an alloc whose size is only known after the frame is compiled,
+    /// returning where to patch it, see `patch_alloc`
+    fn op_alloc(&mut self) -> usize {
+        self.op_args(OpCode::Alloc, &[0])
+    }
+
+    fn patch_alloc(&mut self, pos: usize) -> Result<()> {
+        if self.slots > MAX_SLOTS {
+            return self.error(format!(
+                "mais de {MAX_SLOTS} variáveis na mesma função ou no programa principal"
+            ));
+        }
+        self.insert_addr(self.slots, pos);
+        Ok(())
+    }
+
+    fn op_list(&mut self, len: usize) {
+        self.op_args(OpCode::List, &[len]);
     }
 
-    fn push_offset(&mut self, offset: isize) {
-        // include itself on push
-        const SIZE: isize = isize::BITS as isize / 8;
-        let total = offset + (SIZE * offset.signum());
-        self.bytecode.extend(isize::to_ne_bytes(total));
+    fn op_call(&mut self, idt: &'a str, argc: usize) {
+        let pos = self.op_args(OpCode::Call, &[0, argc]); // placeholder for the function address
+        self.calls.push((pos, idt, self.pos.clone()));
     }
 
-    fn insert_offset(&mut self, offset: isize, pos: usize) {
-        let value = isize::to_ne_bytes(offset);
-        self.bytecode[pos..pos + std::mem::size_of::<isize>()].copy_from_slice(&value);
+    fn insert_addr(&mut self, addr: usize, pos: usize) {
+        self.bytecode[pos..pos + ADDR_SIZE].copy_from_slice(&addr_bytes(addr));
+    }
+
+    fn jump_size(&self) -> usize {
+        if self.wide_jumps {
+            WIDE_SIZE
+        } else {
+            JUMP_SIZE
+        }
+    }
+
+    /// emits a jump whose target is not compiled yet, returning where to patch it
+    fn op_jump(&mut self, op: OpCode) -> usize {
+        if self.wide_jumps {
+            self.op(OpCode::Wide);
+        }
+        self.op(op);
+
+        let pos = self.bytecode.len();
+        self.bytecode.resize(pos + self.jump_size(), 0);
+        pos
+    }
+
+    /// emits a jump back to `target`, which is already compiled
+    fn op_jump_back(&mut self, op: OpCode, target: usize) {
+        // offsets count from the end of the jump
+        let end = self.bytecode.len() + 1 + JUMP_SIZE;
+        if let Ok(offset) = i16::try_from(target as isize - end as isize) {
+            self.op(op);
+            self.bytecode.extend(offset.to_le_bytes());
+        } else {
+            let end = self.bytecode.len() + 2 + WIDE_SIZE;
+            self.op(OpCode::Wide);
+            self.op(op);
+            self.bytecode
+                .extend(offs_bytes(target as isize - end as isize));
+        }
+    }
+
+    /// makes the jump whose offset is at `pos` land on the next instruction to be emitted
+    fn patch_jump(&mut self, pos: usize) {
+        self.patch_jump_to(pos, self.bytecode.len());
+    }
+
+    /// makes the jump whose offset is at `pos` land on `target`
+    fn patch_jump_to(&mut self, pos: usize, target: usize) {
+        let size = self.jump_size();
+        let offset = target as isize - (pos + size) as isize;
+
+        if self.wide_jumps {
+            self.bytecode[pos..pos + size].copy_from_slice(&offs_bytes(offset));
+        } else if let Ok(offset) = i16::try_from(offset) {
+            self.bytecode[pos..pos + size].copy_from_slice(&offset.to_le_bytes());
+        } else {
+            self.long_jump = true; // see `compile`
+        }
+    }
+
+    /// compiles a loop body, returning the pending pare and continue jumps
+    fn compile_loop(&mut self, blk: &'a Block<'a>) -> Result<Loop> {
+        self.loops.push(Loop::default());
+        self.compile_block(blk)?;
+        Ok(self.loops.pop().unwrap())
+    }
+
+    /// sets the statement being compiled, the next instructions are attributed to it
+    fn mark(&mut self, pos: TokenPos) {
+        let offset = self.bytecode.len();
+        match self.lines.last_mut() {
+            Some((_, last)) if *last == pos => {}
+            Some((start, last)) if *start == offset => *last = pos.clone(),
+            _ => self.lines.push((offset, pos.clone())),
+        }
+        self.pos = pos;
+    }
+
+    /// error at the statement being compiled
+    fn error<T>(&self, msg: impl Into<String>) -> Result<T> {
+        Err(CompileError {
+            pos: self.pos.clone(),
+            msg: msg.into(),
+        })
+    }
+
+    /// the loop enclosing the current instruction
+    fn current_loop(&mut self, keyword: &str) -> Result<&mut Loop> {
+        if self.loops.is_empty() {
+            return self.error(format!("'{keyword}' fora de um laço"));
+        }
+        Ok(self.loops.last_mut().unwrap())
     }
 
     fn op(&mut self, op: OpCode) {
@@ -77,78 +262,243 @@ impl<'a> Compiler<'a> {
         let addr = self.vi;
         self.get_current_scope().insert(name, addr);
         self.vi += 1;
+        self.slots = self.slots.max(self.vi);
         addr
     }
 
-    fn get_var(&mut self, name: &str) -> usize {
+    fn get_var(&mut self, name: &str) -> Result<usize> {
         for scope in self.scopes.iter().rev() {
             if let Some(addr) = scope.get(name) {
-                return *addr;
+                return Ok(*addr);
             }
         }
 
-        panic!("ERRO: variable '{name}' não definida");
+        self.error(format!("variável '{name}' não definida"))
     }
 
-    pub fn compile(mut self, program: &'a Program<'a>) -> ByteCode {
-        self.compile_block(&program.block);
-        self.bytecode.push(OpCode::Halt as u8);
+    pub fn compile(mut self, program: &'a Program<'a>) -> Result<ByteCode> {
+        let alloc = self.op_alloc();
+        self.compile_block(&program.block)?;
+        self.finish(alloc)?;
+
+        // the size of a forward jump is chosen before its target is known,
+        // when a narrow one cannot reach it, every forward jump is compiled as wide
+        if self.long_jump {
+            let compiler = Compiler {
+                wide_jumps: true,
+                ..Compiler::new()
+            };
+            return compiler.compile(program);
+        }
 
-        ByteCode {
+        Ok(ByteCode {
             bytecode: self.bytecode,
             constants: self.constants,
+            lines: self.lines,
+        })
+    }
+
+    /// compiles statements that run after the ones compiled before, as the repl does.
+    /// Variables of `block` stay declared and values of bare expressions are printed.
+    /// The result only has the new code and constants, see `LinaVm::append`
+    pub fn compile_more(&mut self, block: &'a Block<'a>) -> Result<ByteCode> {
+        let (code, constants, lines) =
+            (self.bytecode.len(), self.constants.len(), self.lines.len());
+        let (vi, slots, globals) = (self.vi, self.slots, self.scopes[0].clone());
+
+        loop {
+            // the frame of the main program grows with the variables of each input
+            let alloc = self.op_alloc();
+            let result = block
+                .iter_stmts()
+                .try_for_each(|stmt| self.compile_echo(stmt))
+                .and_then(|_| self.finish(alloc));
+
+            // a narrow forward jump was too short, see `compile`
+            let retry = result.is_ok() && std::mem::take(&mut self.long_jump);
+
+            if result.is_err() || retry {
+                self.bytecode.truncate(code);
+                self.constants.truncate(constants);
+                self.lines.truncate(lines);
+                self.functions.retain(|_, addr| *addr < code);
+                self.pending.clear();
+                self.calls.clear();
+                self.loops.clear();
+                self.vi = vi;
+                self.slots = slots;
+                self.scopes = vec![globals.clone()];
+            }
+
+            if retry {
+                self.wide_jumps = true;
+                continue;
+            }
+            result?;
+            break;
+        }
+
+        Ok(ByteCode {
+            bytecode: self.bytecode[code..].to_vec(),
+            constants: self.constants[constants..].to_vec(),
+            lines: self.lines[lines..].to_vec(),
+        })
+    }
+
+    /// compiles a top level statement, printing the value when it is a bare expression
+    fn compile_echo(&mut self, instr: &'a SyntaxTree<'a>) -> Result<()> {
+        match instr {
+            SyntaxTree::Expr { pos, exp }
+                if exp.get_type() != Type::Void
+                    && !matches!(exp, Expression::BinOp { ope, .. } if ope.is_atrib()) =>
+            {
+                self.mark(pos.clone());
+                self.compile_expr(exp)?;
+                self.op(OpCode::Write);
+                self.compile_literal(&Literal::Texto("\n".into()));
+                self.op(OpCode::Write);
+                Ok(())
+            }
+            _ => self.compile_instruction(instr),
         }
     }
 
-    fn compile_block(&mut self, block: &'a Block) {
+    /// ends the main code, whose frame is allocated at `alloc`, and places the functions after it
+    fn finish(&mut self, alloc: usize) -> Result<()> {
+        self.bytecode.push(OpCode::Halt as u8);
+        self.patch_alloc(alloc)?;
+
+        // functions are placed after the program, which halts before reaching them
+        for function in std::mem::take(&mut self.pending) {
+            if let SyntaxTree::FuncaoStmt {
+                pos,
+                idt,
+                par,
+                ret,
+                blk,
+            } = function
+            {
+                self.mark(pos.clone());
+                self.compile_function(idt, par, ret, blk)?;
+            }
+        }
+
+        for (pos, idt, call) in std::mem::take(&mut self.calls) {
+            let Some(&addr) = self.functions.get(idt) else {
+                self.pos = call;
+                return self.error(format!("função '{idt}' não definida"));
+            };
+            self.insert_addr(addr, pos);
+        }
+
+        // statements that ended without code cover nothing
+        while let Some((start, _)) = self.lines.last() {
+            if *start != self.bytecode.len() {
+                break;
+            }
+            self.lines.pop();
+        }
+
+        Ok(())
+    }
+
+    fn compile_function(
+        &mut self,
+        idt: &'a str,
+        par: &'a [(&'a str, Type)],
+        ret: &Type,
+        blk: &'a Block<'a>,
+    ) -> Result<()> {
+        self.functions.insert(idt, self.bytecode.len());
+
+        // the arguments are the first variables of the call frame
+        let scopes = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+        let vi = std::mem::replace(&mut self.vi, 0);
+        let slots = std::mem::replace(&mut self.slots, 0);
+        for (idt, _) in par {
+            self.set_var(idt);
+        }
+
+        let alloc = self.op_alloc();
+        self.compile_block(blk)?;
+
+        if *ret == Type::Void {
+            self.op(OpCode::ReturnV);
+        }
+        self.patch_alloc(alloc)?;
+
+        self.scopes = scopes;
+        self.vi = vi;
+        self.slots = slots;
+        Ok(())
+    }
+
+    fn compile_block(&mut self, block: &'a Block<'a>) -> Result<()> {
         self.enter_scope();
         for instr in block.iter_stmts() {
-            self.compile_instruction(instr);
+            self.compile_instruction(instr)?;
         }
         self.exit_scope();
+        Ok(())
     }
 
-    fn compile_instruction(&mut self, instr: &'a SyntaxTree) {
+    fn compile_instruction(&mut self, instr: &'a SyntaxTree<'a>) -> Result<()> {
+        let outer = self.pos.clone();
+        self.mark(instr.pos().clone());
         match instr {
             SyntaxTree::Assign { idt, exp, .. } => {
                 let addr = self.set_var(idt);
-                self.compile_expr(exp);
+                self.compile_expr(exp)?;
                 self.op_store(addr);
             }
-            SyntaxTree::SeStmt { exp, blk } => {
-                self.compile_expr(exp);
-                self.op(OpCode::JmpF); // jump if expression is false
+            SyntaxTree::SeStmt {
+                exp, blk, alt, els, ..
+            } => {
+                let branches = std::iter::once((exp, blk)).chain(alt.iter().map(|(e, b)| (e, b)));
+                let last = alt.len();
+                let mut exits = Vec::new(); // jumps to the end of the whole chain
+
+                for (i, (exp, blk)) in branches.enumerate() {
+                    self.compile_expr(exp)?;
+                    // jump to the next branch if expression is false
+                    let next = self.op_jump(OpCode::JmpF);
+
+                    self.compile_block(blk)?;
+
+                    // a taken branch skips every branch after it, unless it returns,
+                    // which at the end of a function would jump past its code
+                    if (i < last || els.is_some()) && !blk.returns() {
+                        let exit = self.op_jump(OpCode::Jmp);
+                        exits.push(exit);
+                    }
 
-                let jmp_offset_pos = self.bytecode.len(); // offset pos
-                self.push_offset(0); // placeholder for jump offset
+                    self.patch_jump(next);
+                }
 
-                let start = self.bytecode.len(); // start of block
-                self.compile_block(blk);
-                let end = self.bytecode.len(); // end of block
+                if let Some(els) = els {
+                    self.compile_block(els)?;
+                }
 
-                let jmp_offset = (end - start) as isize; // length of block
-                self.insert_offset(jmp_offset, jmp_offset_pos); // jump over the block
+                for pos in exits {
+                    self.patch_jump(pos);
+                }
             }
-            SyntaxTree::EnquantoStmt { exp, blk } => {
+            SyntaxTree::EnquantoStmt { exp, blk, .. } => {
                 let start = self.bytecode.len(); // start while expression
 
-                self.compile_expr(exp);
-                self.op(OpCode::JmpF);
-
-                let jmpf_offset_pos = self.bytecode.len();
-                self.push_offset(0); // placeholder for the jump out
-
-                let block_start = self.bytecode.len();
-                self.compile_block(blk);
-                self.op(OpCode::Jmp);
+                self.compile_expr(exp)?;
+                let exit = self.op_jump(OpCode::JmpF);
 
-                let end = self.bytecode.len(); //  end while expression
-                let jmp_offset = (end - start) as isize;
-                self.push_offset(-jmp_offset); // jmp will go back to expr evaluation
+                let jumps = self.compile_loop(blk)?;
+                self.op_jump_back(OpCode::Jmp, start); // back to the expression
+                self.patch_jump(exit);
 
-                let end = self.bytecode.len();
-                let jmp_offset = (end - block_start) as isize; // this will skip the block and jmp
-                self.insert_offset(jmp_offset, jmpf_offset_pos);
+                for pos in jumps.continues {
+                    self.patch_jump_to(pos, start);
+                }
+                for pos in jumps.breaks {
+                    self.patch_jump(pos);
+                }
             }
             SyntaxTree::ParaStmt {
                 idt,
@@ -156,66 +506,84 @@ impl<'a> Compiler<'a> {
                 lmt,
                 stp,
                 blk,
+                ..
             } => {
                 let addr = self.set_var(idt);
-                self.compile_literal(sta.as_ref().unwrap_or(&Literal::Inteiro(0)));
+                self.compile_literal(sta.as_ref().map_or(&Literal::Inteiro(0), |(sta, _)| sta));
                 self.op_store(addr);
 
                 let start = self.bytecode.len();
 
-                match lmt.get_type() {
+                let exit = match lmt.get_type() {
                     Type::Integer | Type::Real => {
                         self.op_load(addr);
-                        self.compile_expr(lmt);
+                        self.compile_expr(lmt)?;
                         self.op(OpCode::LE);
-                        self.op(OpCode::JmpF);
+                        self.op_jump(OpCode::JmpF)
                     }
                     Type::Boolean => {
-                        self.compile_expr(lmt);
-                        self.op(OpCode::JmpT);
+                        self.compile_expr(lmt)?;
+                        self.op_jump(OpCode::JmpT)
                     }
-                    _ => panic!("ERRO: tipo não permitido como limite"),
+                    typ => return self.error(format!("tipo não permitido como limite: {typ}")),
                 };
 
-                let jmp_offset_pos = self.bytecode.len();
-                self.push_offset(0);
+                let jumps = self.compile_loop(blk)?;
 
-                let block_start = self.bytecode.len();
-                self.compile_block(blk);
+                for pos in jumps.continues {
+                    self.patch_jump(pos); // continue goes to the increment
+                }
 
                 self.op_load(addr);
-                self.compile_literal(stp.as_ref().unwrap_or(&Literal::Inteiro(1)));
+                self.compile_literal(stp.as_ref().map_or(&Literal::Inteiro(1), |(stp, _)| stp));
                 self.op(OpCode::Add);
                 self.op_store(addr);
 
-                self.op(OpCode::Jmp);
+                self.op_jump_back(OpCode::Jmp, start);
+                self.patch_jump(exit);
 
-                let end = self.bytecode.len();
-                let jmp_offset = (end - start) as isize;
-                self.push_offset(-jmp_offset);
-
-                let end = self.bytecode.len();
-                let jmp_offset = (end - block_start) as isize;
-                self.insert_offset(jmp_offset, jmp_offset_pos)
+                for pos in jumps.breaks {
+                    self.patch_jump(pos);
+                }
             }
-            SyntaxTree::Expr(expr) => {
-                self.compile_expr(expr);
-                if expr.get_type() != Type::Void {
+            SyntaxTree::FuncaoStmt { .. } => self.pending.push(instr),
+            SyntaxTree::RetorneStmt { exp: Some(exp), .. } => {
+                self.compile_expr(exp)?;
+                self.op(OpCode::Return);
+            }
+            SyntaxTree::RetorneStmt { exp: None, .. } => self.op(OpCode::ReturnV),
+            SyntaxTree::PareStmt { .. } => {
+                let pos = self.op_jump(OpCode::Jmp);
+                self.current_loop("pare")?.breaks.push(pos);
+            }
+            SyntaxTree::ContinueStmt { .. } => {
+                let pos = self.op_jump(OpCode::Jmp);
+                self.current_loop("continue")?.continues.push(pos);
+            }
+            SyntaxTree::Expr { exp, .. } => {
+                self.compile_expr(exp)?;
+                if exp.get_type() != Type::Void {
                     self.op(OpCode::Pop);
                 }
             }
         }
+        self.mark(outer); // code after a nested statement belongs to the enclosing one
+        Ok(())
     }
 
     fn compile_literal(&mut self, literal: &Literal) {
-        let value = match *literal {
-            Literal::Decimal(number) => LinaValue::Float32(number),
-            Literal::Inteiro(number) => LinaValue::Int32(number),
-            Literal::Texto(text) => LinaValue::String(String::from(text)),
-            Literal::Booleano(boolean) => LinaValue::Boolean(boolean),
+        let value = match literal {
+            Literal::Decimal(number) => LinaValue::Float64(*number),
+            Literal::Inteiro(number) => LinaValue::Int64(*number),
+            Literal::Texto(text) => LinaValue::String(text.to_string()),
+            Literal::Booleano(boolean) => LinaValue::Boolean(*boolean),
         };
 
-        let find = self.constants.iter().position(|v| *v == value);
+        // reals are compared by their bits, since -0.0 == 0.0 but 1.0 / -0.0 is -inf
+        let find = self.constants.iter().position(|v| match (v, &value) {
+            (LinaValue::Float64(a), LinaValue::Float64(b)) => a.to_bits() == b.to_bits(),
+            (v, value) => v == value,
+        });
         let addr = match find {
             Some(i) => i,
             None => {
@@ -227,72 +595,136 @@ impl<'a> Compiler<'a> {
         self.op_const(addr);
     }
 
-    fn compile_expr(&mut self, expr: &Expression) {
+    /// emits the operation of `ope`, `typ` being the type of the left hand side
+    fn compile_operator(&mut self, ope: &Operador, typ: &Type) {
+        match ope {
+            Operador::MaiorQue => self.op(OpCode::GT),
+            Operador::MenorQue => self.op(OpCode::LT),
+            Operador::MaiorIgualQue => self.op(OpCode::GE),
+            Operador::MenorIgualQue => self.op(OpCode::LE),
+            Operador::Igual => self.op(OpCode::Eq),
+            Operador::Diferente => self.op(OpCode::NE),
+
+            Operador::E => self.op(OpCode::And),
+            Operador::Ou => self.op(OpCode::Or),
+            Operador::Nao => self.op(OpCode::Not),
+
+            Operador::Adic | Operador::AdicAtrib => match typ {
+                Type::Text => self.op(OpCode::Concat),
+                _ => self.op(OpCode::Add),
+            },
+
+            Operador::Subt | Operador::SubtAtrib => self.op(OpCode::Sub),
+            Operador::Mult | Operador::MultAtrib => self.op(OpCode::Mul),
+            Operador::Div | Operador::DivAtrib => self.op(OpCode::Div),
+
+            Operador::Resto | Operador::RestoAtrib => self.op(OpCode::Rem),
+            Operador::Exp | Operador::ExpAtrib => self.op(OpCode::Pow),
+
+            Operador::Atrib => {}
+        };
+    }
+
+    fn compile_expr(&mut self, expr: &'a Expression<'a>) -> Result<()> {
         match expr {
-            Expression::Literal(literal) => self.compile_literal(literal),
+            Expression::Literal(literal, _) => self.compile_literal(literal),
             Expression::Identifier(idt, ..) => {
-                let addr = self.get_var(idt);
+                let addr = self.get_var(idt)?;
                 self.op_load(addr);
             }
             Expression::BinOp { ope, lhs, rhs, .. } => {
+                if let (true, Expression::Index { lst, idx, .. }) = (ope.is_atrib(), lhs.as_ref()) {
+                    self.compile_expr(lst)?;
+                    self.compile_expr(idx)?;
+                    if *ope != Operador::Atrib {
+                        self.op(OpCode::Dup2); // keep list and index for the SetIndex
+                        self.op(OpCode::Index);
+                    }
+                    self.compile_expr(rhs)?;
+                    self.compile_operator(ope, &lhs.get_type());
+                    self.op(OpCode::SetIndex);
+                    return Ok(());
+                }
+
                 // Atrib (:=) does not need a left hand side
                 if *ope != Operador::Atrib {
-                    self.compile_expr(lhs);
+                    self.compile_expr(lhs)?;
                 }
-                self.compile_expr(rhs);
-
-                match ope {
-                    Operador::MaiorQue => self.op(OpCode::GT),
-                    Operador::MenorQue => self.op(OpCode::LT),
-                    Operador::MaiorIgualQue => self.op(OpCode::GE),
-                    Operador::MenorIgualQue => self.op(OpCode::LE),
-                    Operador::Igual => self.op(OpCode::Eq),
-                    Operador::Diferente => self.op(OpCode::NE),
-
-                    Operador::E => self.op(OpCode::And),
-                    Operador::Ou => self.op(OpCode::Or),
-
-                    Operador::Adic | Operador::AdicAtrib => match lhs.get_type() {
-                        Type::Text => self.op(OpCode::Concat),
-                        _ => self.op(OpCode::Add),
-                    },
-
-                    Operador::Subt | Operador::SubtAtrib => self.op(OpCode::Sub),
-                    Operador::Mult | Operador::MultAtrib => self.op(OpCode::Mul),
-                    Operador::Div | Operador::DivAtrib => self.op(OpCode::Div),
-
-                    Operador::Resto | Operador::RestoAtrib => self.op(OpCode::Rem),
-                    Operador::Exp | Operador::ExpAtrib => todo!(),
-
-                    Operador::Atrib => {}
-                };
+                self.compile_expr(rhs)?;
+                self.compile_operator(ope, &lhs.get_type());
 
                 if ope.is_atrib() {
                     let Expression::Identifier(idt, _typ) = *lhs.to_owned() else {
-                        panic!("ERRO: lado esquerdo de uma atribuição deve ser um identificador");
+                        return self
+                            .error("lado esquerdo de uma atribuição deve ser um identificador");
                     };
                     self.op(OpCode::Dup);
-                    let addr = self.get_var(idt);
+                    let addr = self.get_var(idt)?;
                     self.op_store(addr);
                 }
             }
+            Expression::UnOp { ope, exp, .. } => {
+                self.compile_expr(exp)?;
+                match ope {
+                    Operador::Subt => self.op(OpCode::Neg),
+                    Operador::Nao => self.op(OpCode::Not),
+                    _ => return self.error(format!("operador unário não suportado: {ope}")),
+                }
+            }
             Expression::Cast(exp, typ) => {
-                self.compile_expr(exp);
+                self.compile_expr(exp)?;
                 match typ {
                     Type::Integer => self.op(OpCode::CastI),
                     Type::Real => self.op(OpCode::CastF),
                     Type::Text => self.op(OpCode::CastS),
-                    _ => panic!("ERRO: nenhuma função de cast para o tipo: {typ}"),
+                    _ => return self.error(format!("nenhuma função de cast para o tipo: {typ}")),
+                }
+            }
+            Expression::List { exp, .. } => {
+                for item in exp {
+                    self.compile_expr(item)?;
                 }
+                self.op_list(exp.len());
+            }
+            Expression::Index { lst, idx, .. } => {
+                self.compile_expr(lst)?;
+                self.compile_expr(idx)?;
+                self.op(OpCode::Index);
+            }
+            Expression::Function {
+                idt: "tamanho",
+                arg,
+                ..
+            } => {
+                self.compile_expr(&arg[0])?;
+                self.op(OpCode::Len);
+            }
+            Expression::Function {
+                idt: "adicionar",
+                arg,
+                ..
+            } => {
+                self.compile_expr(&arg[0])?;
+                self.compile_expr(&arg[1])?;
+                self.op(OpCode::Append);
+            }
+            Expression::Function {
+                idt: "remover",
+                arg,
+                ..
+            } => {
+                self.compile_expr(&arg[0])?;
+                self.compile_expr(&arg[1])?;
+                self.op(OpCode::Remove);
             }
             Expression::Function {
                 idt: "saida", arg, ..
             } => {
                 for exp in arg {
-                    self.compile_expr(exp);
+                    self.compile_expr(exp)?;
                     self.op(OpCode::Write);
                 }
-                self.compile_literal(&Literal::Texto("\n"));
+                self.compile_literal(&Literal::Texto("\n".into()));
                 self.op(OpCode::Write);
             }
             Expression::Function {
@@ -302,25 +734,56 @@ impl<'a> Compiler<'a> {
             } => {
                 for exp in arg {
                     let Expression::Identifier(idt, typ) = exp else {
-                        panic!("ERRO: argumento deve ser um identificador")
+                        return self.error("argumento de entrada deve ser um identificador");
                     };
 
-                    let addr = self.get_var(idt);
+                    let addr = self.get_var(idt)?;
                     match typ {
                         Type::Integer => self.op(OpCode::ReadI),
                         Type::Real => self.op(OpCode::ReadF),
                         Type::Text => self.op(OpCode::ReadL),
-                        _ => panic!("ERRO: leitura não suportada para o tipo: {typ}"),
+                        _ => {
+                            return self.error(format!("leitura não suportada para o tipo: {typ}"))
+                        }
                     }
 
                     self.op_store(addr);
                 }
             }
-            Expression::Function { .. } => todo!(),
+            Expression::Function { idt, arg, .. } => {
+                for exp in arg {
+                    self.compile_expr(exp)?;
+                }
+                self.op_call(idt, arg.len());
+            }
+            Expression::Interpolation(exp) => {
+                // the parser already cast every piece to texto
+                for (i, piece) in exp.iter().enumerate() {
+                    self.compile_expr(piece)?;
+                    if i > 0 {
+                        self.op(OpCode::Concat);
+                    }
+                }
+            }
         }
+        Ok(())
     }
 }
 
-pub fn compile<'a>(program: &'a Program<'a>) -> ByteCode {
+pub fn compile<'a>(program: &'a Program<'a>) -> Result<ByteCode> {
     Compiler::new().compile(program)
 }
+
+/// the code of `exp` alone, leaving its value on the stack when it halts.
+/// It has no frame, so `exp` must not use variables nor call functions
+pub fn compile_constant<'a>(exp: &'a Expression<'a>) -> Result<ByteCode> {
+    let mut compiler = Compiler::new();
+    compiler.compile_expr(exp)?;
+    compiler.op(OpCode::Halt);
+
+    Ok(ByteCode {
+        bytecode: compiler.bytecode,
+        constants: compiler.constants,
+        lines: Vec::new(),
+    })
+}
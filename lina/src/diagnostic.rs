@@ -0,0 +1,219 @@
+use std::fmt::Write;
+
+use crate::compiler::CompileError;
+use crate::lexer::LexicalError;
+use crate::parser::SyntaxError;
+use crate::token::TokenPos;
+use crate::vm::{RuntimeError, VmError};
+
+/// a secondary place of the source related to an error, like a previous declaration
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub pos: TokenPos,
+    pub end: Option<TokenPos>, // None marks a single character
+    pub msg: String,
+}
+
+impl Label {
+    pub fn new(pos: TokenPos, msg: impl Into<String>) -> Self {
+        Self {
+            pos,
+            end: None,
+            msg: msg.into(),
+        }
+    }
+}
+
+/// an error ready to be shown with the source lines it refers to
+pub struct Diagnostic {
+    pub title: &'static str,
+    pub msg: String,
+    pub pos: TokenPos,
+    pub end: Option<TokenPos>,
+    pub labels: Vec<Label>,
+    pub hint: Option<String>,
+}
+
+/// one underline drawn below a source line
+struct Mark<'a> {
+    pos: &'a TokenPos,
+    end: Option<&'a TokenPos>,
+    ch: char,
+    msg: &'a str,
+}
+
+impl Diagnostic {
+    pub fn new(title: &'static str, msg: impl Into<String>, pos: TokenPos) -> Self {
+        Self {
+            title,
+            msg: msg.into(),
+            pos,
+            end: None,
+            labels: Vec::new(),
+            hint: None,
+        }
+    }
+
+    /// the error in the style of rustc, with the lines of `code` it points at:
+    ///
+    /// ```text
+    /// Erro Sintático: redeclaração da variável x
+    ///  --> main.lina:3:0
+    ///   |
+    /// 2 | seja x := 1
+    ///   | ---- variável declarada aqui
+    /// 3 | seja x := 2
+    ///   | ^^^^
+    ///   |
+    ///   = dica: para mudar o valor de x, use `x := ...` sem declarar de novo
+    /// ```
+    pub fn render(&self, file: &str, code: &str) -> String {
+        let lines = code.lines().collect::<Vec<_>>();
+
+        let mut marks = vec![Mark {
+            pos: &self.pos,
+            end: self.end.as_ref(),
+            ch: '^',
+            msg: "",
+        }];
+        marks.extend(self.labels.iter().map(|label| Mark {
+            pos: &label.pos,
+            end: label.end.as_ref(),
+            ch: '-',
+            msg: &label.msg,
+        }));
+        marks.retain(|mark| (1..=lines.len()).contains(&mark.pos.row));
+        marks.sort_by_key(|mark| (mark.pos.row, mark.pos.col));
+
+        let width = marks
+            .iter()
+            .map(|mark| mark.pos.row.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(width);
+
+        let mut out = String::new();
+        _ = writeln!(out, "{}: {}", self.title, self.msg);
+        // programs compiled without debug info do not know where the error is
+        if self.pos.row > 0 {
+            _ = writeln!(out, "{pad}--> {file}:{}:{}", self.pos.row, self.pos.col);
+        }
+
+        if !marks.is_empty() {
+            _ = writeln!(out, "{pad} |");
+        }
+
+        let mut prev: Option<usize> = None;
+        for mark in &marks {
+            let row = mark.pos.row;
+            let line = lines[row - 1];
+
+            if prev != Some(row) {
+                if prev.is_some_and(|prev| row > prev + 1) {
+                    _ = writeln!(out, "...");
+                }
+                _ = writeln!(out, "{row:>width$} | {line}");
+                prev = Some(row);
+            }
+
+            let underline = underline(line, mark);
+            if mark.msg.is_empty() {
+                _ = writeln!(out, "{pad} | {underline}");
+            } else {
+                _ = writeln!(out, "{pad} | {underline} {}", mark.msg);
+            }
+        }
+
+        if let Some(hint) = &self.hint {
+            if !marks.is_empty() {
+                _ = writeln!(out, "{pad} |");
+            }
+            _ = writeln!(out, "{pad} = dica: {hint}");
+        }
+
+        out
+    }
+}
+
+/// spaces up to the mark, keeping tabs so it lines up, and then the mark itself
+fn underline(line: &str, mark: &Mark) -> String {
+    let start = floor_char_boundary(line, mark.pos.col);
+    let stop = match mark.end {
+        Some(end) if end.row == mark.pos.row => floor_char_boundary(line, end.col),
+        Some(_) => line.len(), // spans more than one line, marks up to its end
+        None => start,
+    };
+
+    let mut out = line[..start]
+        .chars()
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+
+    let len = line[start..stop.max(start)].chars().count().max(1);
+    out.extend(std::iter::repeat_n(mark.ch, len));
+    out
+}
+
+fn floor_char_boundary(line: &str, col: usize) -> usize {
+    let mut col = col.min(line.len());
+    while !line.is_char_boundary(col) {
+        col -= 1;
+    }
+    col
+}
+
+impl From<&LexicalError> for Diagnostic {
+    fn from(err: &LexicalError) -> Self {
+        let pos = TokenPos {
+            row: err.row,
+            col: err.col,
+        };
+        Diagnostic::new("Erro Léxico", err.msg.clone(), pos)
+    }
+}
+
+impl From<&SyntaxError> for Diagnostic {
+    fn from(err: &SyntaxError) -> Self {
+        Self {
+            end: err.end.clone(),
+            labels: err.labels.clone(),
+            hint: err.hint.clone(),
+            ..Diagnostic::new("Erro Sintático", err.msg.clone(), err.pos.clone())
+        }
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(err: &CompileError) -> Self {
+        Diagnostic::new("Erro de Compilação", err.msg.clone(), err.pos.clone())
+    }
+}
+
+impl From<&VmError> for Diagnostic {
+    fn from(err: &VmError) -> Self {
+        let hint = match &err.err {
+            RuntimeError::IndiceInvalido { len, .. } if *len > 0 => {
+                Some(format!("os índices desta lista vão de 0 até {}", len - 1))
+            }
+            RuntimeError::IndiceInvalido { .. } => Some("a lista está vazia".into()),
+            RuntimeError::DivisaoPorZero(_) => {
+                Some("verifique se o divisor é diferente de 0 antes de dividir".into())
+            }
+            RuntimeError::Estouro(_) => {
+                Some("use o tipo real para números maiores do que um inteiro comporta".into())
+            }
+            RuntimeError::EstouroDePilha => Some(
+                "uma função recursiva precisa de um caso que retorne sem chamar a si mesma".into(),
+            ),
+            RuntimeError::ParseIntError(_) | RuntimeError::ParseFloatError(_) => {
+                Some("o valor digitado não é um número válido".into())
+            }
+            _ => None,
+        };
+
+        Self {
+            hint,
+            ..Diagnostic::new("Erro de Execução", err.err.to_string(), err.pos.clone())
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use crate::syntax::{Block, Declaration, Expression, Program, SyntaxTree, Type};
+use crate::token::{Comment, Literal, OpAssoc, OpInfo, Operador, TokenPos};
+
+const INDENT: &str = "    ";
+
+/// how tightly a printed expression holds together
+enum Shape {
+    Atom,
+    Prefix(u8), // extends to the right over operators with at least this precedence
+    Infix(u8),
+}
+
+fn shape(exp: &Expression) -> Shape {
+    match exp {
+        Expression::Cast(exp, _) => shape(exp),
+        Expression::BinOp { ope, .. } => Shape::Infix(ope.precedence().0),
+        Expression::UnOp { ope, .. } => Shape::Prefix(ope.unary_precedence().unwrap_or(0)),
+        Expression::Literal(Literal::Inteiro(number), _) if *number < 0 => Shape::Prefix(8),
+        Expression::Literal(Literal::Decimal(number), _) if number.is_sign_negative() => {
+            Shape::Prefix(8)
+        }
+        _ => Shape::Atom,
+    }
+}
+
+fn literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Decimal(number) => format!("{number:?}"), // keeps the .0 of whole numbers
+        Literal::Inteiro(number) => format!("{number}"),
+        Literal::Texto(text) => format!("\"{}\"", escape(text)),
+        Literal::Booleano(true) => "verdadeiro".into(),
+        Literal::Booleano(false) => "falso".into(),
+    }
+}
+
+/// writes `text` back with the escape sequences the lexer reads
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '{' => out.push_str("\\{"),
+            '}' => out.push_str("\\}"),
+            ch if ch.is_control() => out.push_str(&format!("\\u{{{:x}}}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+fn before(a: &TokenPos, b: &TokenPos) -> bool {
+    (a.row, a.col) < (b.row, b.col)
+}
+
+/// writes a program back as source code, with minimal parentheses and its comments
+pub struct Formatter<'a> {
+    lines: Vec<&'a str>, // source lines, to keep blank lines and trailing comments
+    comments: Peekable<IntoIter<Comment<'a>>>,
+    out: String,
+    depth: usize,
+    fresh: bool, // nothing was written in the current block yet
+}
+
+impl<'a> Formatter<'a> {
+    pub fn new(code: &'a str, comments: Vec<Comment<'a>>) -> Self {
+        Self {
+            lines: code.lines().collect(),
+            comments: comments.into_iter().peekable(),
+            out: String::new(),
+            depth: 0,
+            fresh: true,
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.fresh = false;
+    }
+
+    /// keeps one of the blank lines the source had before `row`
+    fn blank_before(&mut self, row: usize) {
+        let blank = row >= 2 && self.lines[row - 2].trim().is_empty();
+        if blank && !self.fresh && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn is_trailing(&self, comment: &Comment) -> bool {
+        let line = self.lines[comment.pos.row - 1];
+        !self.out.is_empty() && !line[..comment.pos.col].trim().is_empty()
+    }
+
+    fn comment(&mut self, comment: Comment) {
+        if self.is_trailing(&comment) {
+            // stays at the end of the line it was written on
+            self.out.pop();
+            self.out.push(' ');
+            self.out.push_str(comment.text);
+            self.out.push('\n');
+        } else {
+            self.blank_before(comment.pos.row);
+            self.line(comment.text);
+        }
+    }
+
+    fn comments_before(&mut self, pos: &TokenPos) {
+        while let Some(comment) = self.comments.next_if(|c| before(&c.pos, pos)) {
+            self.comment(comment);
+        }
+    }
+
+    fn has_comments_before(&mut self, pos: &TokenPos) -> bool {
+        self.comments.peek().is_some_and(|c| before(&c.pos, pos))
+    }
+
+    /// `exp` where an infix operand needs at least `min` precedence to go without
+    /// parentheses, followed by an operator of precedence `follow`, 0 if none
+    fn operand(&self, exp: &Expression, min: u8, follow: u8) -> String {
+        let parens = match shape(exp) {
+            Shape::Atom => false,
+            Shape::Infix(prec) => prec < min,
+            Shape::Prefix(prec) => follow >= prec,
+        };
+
+        if parens {
+            format!("({})", self.expression(exp, 0))
+        } else {
+            self.expression(exp, follow)
+        }
+    }
+
+    fn expression(&self, exp: &Expression, follow: u8) -> String {
+        match exp {
+            // literals are written as in the code, like 0xFF or a text with line
+            // breaks, and only those folded or made up by the parser from their value
+            Expression::Literal(_, Some(src)) => src.to_string(),
+            Expression::Literal(lit, None) => literal(lit),
+            Expression::Identifier(idt, _) => idt.to_string(),
+            Expression::Cast(exp, _) => self.expression(exp, follow), // casts are implicit
+            Expression::BinOp { ope, lhs, rhs, .. } => {
+                let OpInfo(prec, assoc) = ope.precedence();
+                let (min_lhs, min_rhs) = match assoc {
+                    OpAssoc::L => (prec, prec + 1),
+                    OpAssoc::R => (prec + 1, prec),
+                };
+                let lhs = self.operand(lhs, min_lhs, prec);
+                let rhs = self.operand(rhs, min_rhs, follow);
+                format!("{lhs} {ope} {rhs}")
+            }
+            Expression::UnOp { ope, exp, .. } => {
+                let prec = ope.unary_precedence().unwrap_or(0);
+                let exp = self.operand(exp, prec, follow);
+                match ope {
+                    Operador::Subt if exp.starts_with('-') => format!("-({exp})"),
+                    Operador::Subt => format!("-{exp}"),
+                    _ => format!("{ope} {exp}"),
+                }
+            }
+            Expression::List { exp, .. } => format!("[{}]", self.arguments(exp)),
+            Expression::Index { lst, idx, .. } => {
+                let lst = self.operand(lst, u8::MAX, u8::MAX);
+                format!("{lst}[{}]", self.expression(idx, 0))
+            }
+            Expression::Function { idt, arg, .. } => format!("{idt}({})", self.arguments(arg)),
+            Expression::Interpolation(exp) => {
+                let mut text = String::from('"');
+                for piece in exp {
+                    match piece {
+                        Expression::Literal(Literal::Texto(_), Some(src)) => text += src,
+                        Expression::Literal(Literal::Texto(piece), None) => text += &escape(piece),
+                        piece => text += &format!("{{{}}}", self.expression(piece, 0)),
+                    }
+                }
+                text.push('"');
+                text
+            }
+        }
+    }
+
+    fn arguments(&self, exp: &[Expression]) -> String {
+        exp.iter()
+            .map(|exp| self.expression(exp, 0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// statements that fit in a single line
+    fn simple(&self, stmt: &SyntaxTree) -> Option<String> {
+        let text = match stmt {
+            SyntaxTree::Assign {
+                dcl: Declaration::Seja,
+                idt,
+                exp,
+                ..
+            } => format!("seja {idt} := {}", self.expression(exp, 0)),
+            SyntaxTree::Assign {
+                dcl: Declaration::Typed,
+                typ,
+                idt,
+                exp,
+                ..
+            } => format!("{typ} {idt} := {}", self.expression(exp, 0)),
+            SyntaxTree::Assign {
+                dcl: Declaration::Default,
+                typ,
+                idt,
+                ..
+            } => format!("{typ} {idt}"),
+            SyntaxTree::Expr { exp, .. } => self.expression(exp, 0),
+            SyntaxTree::RetorneStmt { exp: Some(exp), .. } => {
+                format!("retorne {}", self.expression(exp, 0))
+            }
+            SyntaxTree::RetorneStmt { exp: None, .. } => "retorne".into(),
+            SyntaxTree::PareStmt { .. } => "pare".into(),
+            SyntaxTree::ContinueStmt { .. } => "continue".into(),
+            _ => return None,
+        };
+        Some(text)
+    }
+
+    /// the only statement of `blk` when it was written in the same line as `pos`,
+    /// like `se x entao saida(x) fim`, so it stays that way
+    fn single_line(&mut self, pos: &TokenPos, blk: &Block) -> Option<String> {
+        match blk.iter_stmts().as_slice() {
+            [stmt] if stmt.pos().row == pos.row && !self.has_comments_before(blk.end()) => {
+                self.simple(stmt)
+            }
+            _ => None,
+        }
+    }
+
+    fn block(&mut self, blk: &Block) {
+        self.depth += 1;
+        self.fresh = true;
+        for stmt in blk.iter_stmts() {
+            self.statement(stmt);
+        }
+        self.comments_before(blk.end());
+        self.depth -= 1;
+    }
+
+    fn statement(&mut self, stmt: &SyntaxTree) {
+        self.comments_before(stmt.pos());
+        self.blank_before(stmt.pos().row);
+
+        if let Some(text) = self.simple(stmt) {
+            self.line(&text);
+            return;
+        }
+
+        match stmt {
+            SyntaxTree::SeStmt {
+                pos,
+                exp,
+                blk,
+                alt,
+                els,
+            } => {
+                let exp = self.expression(exp, 0);
+
+                if alt.is_empty() && els.is_none() {
+                    if let Some(text) = self.single_line(pos, blk) {
+                        self.line(&format!("se {exp} entao {text} fim"));
+                        return;
+                    }
+                }
+
+                self.line(&format!("se {exp} entao"));
+                self.block(blk);
+                for (exp, blk) in alt {
+                    let exp = self.expression(exp, 0);
+                    self.line(&format!("senao se {exp} entao"));
+                    self.block(blk);
+                }
+                if let Some(els) = els {
+                    self.line("senao");
+                    self.block(els);
+                }
+                self.line("fim");
+            }
+            SyntaxTree::EnquantoStmt { pos, exp, blk } => {
+                let exp = self.expression(exp, 0);
+                if let Some(text) = self.single_line(pos, blk) {
+                    self.line(&format!("enquanto {exp} repetir {text} fim"));
+                    return;
+                }
+                self.line(&format!("enquanto {exp} repetir"));
+                self.block(blk);
+                self.line("fim");
+            }
+            SyntaxTree::ParaStmt {
+                pos,
+                idt,
+                sta,
+                lmt,
+                stp,
+                blk,
+            } => {
+                let mut text = format!("para {idt}");
+                if let Some((_, sta)) = sta {
+                    text += &format!(" := {sta}");
+                }
+                text += &format!(" ate {}", self.expression(lmt, 0));
+                if let Some((_, stp)) = stp {
+                    text += &format!(" incremento {stp}");
+                }
+                if let Some(body) = self.single_line(pos, blk) {
+                    self.line(&format!("{text} repetir {body} fim"));
+                    return;
+                }
+                self.line(&format!("{text} repetir"));
+                self.block(blk);
+                self.line("fim");
+            }
+            SyntaxTree::FuncaoStmt {
+                idt, par, ret, blk, ..
+            } => {
+                let par = par
+                    .iter()
+                    .map(|(idt, typ)| format!("{typ} {idt}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match ret {
+                    Type::Void => self.line(&format!("função {idt}({par})")),
+                    ret => self.line(&format!("função {idt}({par}): {ret}")),
+                }
+                self.block(blk);
+                self.line("fim");
+            }
+            _ => unreachable!("simple statements are written above"),
+        }
+    }
+
+    pub fn format(mut self, program: &Program) -> String {
+        self.comments_before(&program.pos);
+        self.blank_before(program.pos.row);
+        self.line(&format!("programa {}", program.name));
+
+        for stmt in program.block.iter_stmts() {
+            self.statement(stmt);
+        }
+        for comment in self.comments.by_ref().collect::<Vec<_>>() {
+            self.comment(comment);
+        }
+
+        self.out
+    }
+}
+
+/// formats `program`, parsed from `code`, putting back the `comments` found by the lexer
+pub fn format<'a>(program: &Program<'a>, code: &'a str, comments: Vec<Comment<'a>>) -> String {
+    Formatter::new(code, comments).format(program)
+}
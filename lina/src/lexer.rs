@@ -1,7 +1,10 @@
+use std::borrow::Cow;
 use std::char;
 use std::str::Chars;
 
-use crate::token::{Delimitador, Literal, Operador, Token, TokenDef, TokenPos};
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use crate::token::{Comment, Delimitador, Literal, Operador, Parte, Token, TokenDef, TokenPos};
 
 pub struct Lexer<'a> {
     input: &'a str,
@@ -10,6 +13,7 @@ pub struct Lexer<'a> {
     line_num: usize,
     line_start: usize,
     curr_char: Option<char>,
+    comments: Vec<Comment<'a>>,
 }
 
 #[derive(Debug)]
@@ -39,6 +43,7 @@ impl<'a> Lexer<'a> {
             line_num: 1,
             line_start: 0,
             curr_char,
+            comments: Vec::new(),
         }
     }
 
@@ -51,10 +56,10 @@ impl<'a> Lexer<'a> {
     }
 
     fn next_char(&mut self) {
-        self.curr_char = self.char_iter.next();
         if let Some(ch) = self.curr_char {
             self.position += ch.len_utf8();
         }
+        self.curr_char = self.char_iter.next();
     }
 
     fn get_pos(&self) -> TokenPos {
@@ -64,6 +69,29 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    fn peek_char(&self) -> Option<char> {
+        self.char_iter.clone().next()
+    }
+
+    /// called after consuming `c`, starts a new line if it was a line break
+    fn consume_line_break(&mut self, c: char) {
+        match c {
+            '\r' => {
+                if let Some('\n') = self.curr_char {
+                    self.next_char();
+                }
+
+                self.line_num += 1;
+                self.line_start = self.position;
+            }
+            '\n' => {
+                self.line_num += 1;
+                self.line_start = self.position;
+            }
+            _ => {}
+        }
+    }
+
     fn consume_whitespace(&mut self) {
         while let Some(c) = self.curr_char {
             if !c.is_whitespace() {
@@ -71,21 +99,80 @@ impl<'a> Lexer<'a> {
             }
 
             self.next_char();
+            self.consume_line_break(c);
+        }
+    }
 
-            match c {
-                '\r' => {
-                    if let Some('\n') = self.curr_char {
-                        self.next_char();
-                    }
+    fn consume_line_comment(&mut self) {
+        let start = self.position;
+        let pos = self.get_pos();
 
-                    self.line_num += 1;
-                    self.line_start = self.position;
+        while let Some(c) = self.curr_char {
+            if c == '\n' || c == '\r' {
+                break;
+            }
+            self.next_char();
+        }
+
+        self.comments.push(Comment {
+            text: &self.input[start..self.position],
+            pos,
+            block: false,
+        });
+    }
+
+    fn consume_block_comment(&mut self) -> Result<()> {
+        let start = self.position;
+        let pos = self.get_pos();
+
+        // skip the opening /*
+        self.next_char();
+        self.next_char();
+
+        // block comments can be nested
+        let mut depth = 1;
+        while depth > 0 {
+            let Some(c) = self.curr_char else {
+                return Err(LexicalError {
+                    row: pos.row,
+                    col: pos.col,
+                    msg: "comentário de bloco sem */ correspondente".into(),
+                });
+            };
+
+            self.next_char();
+
+            match (c, self.curr_char) {
+                ('*', Some('/')) => {
+                    self.next_char();
+                    depth -= 1;
                 }
-                '\n' => {
-                    self.line_num += 1;
-                    self.line_start = self.position;
+                ('/', Some('*')) => {
+                    self.next_char();
+                    depth += 1;
                 }
-                _ => {}
+                _ => self.consume_line_break(c),
+            }
+        }
+
+        self.comments.push(Comment {
+            text: &self.input[start..self.position],
+            pos,
+            block: true,
+        });
+
+        Ok(())
+    }
+
+    /// skips whitespace and comments, keeping the comments aside
+    fn consume_trivia(&mut self) -> Result<()> {
+        loop {
+            self.consume_whitespace();
+
+            match (self.curr_char, self.peek_char()) {
+                (Some('#'), _) => self.consume_line_comment(),
+                (Some('/'), Some('*')) => self.consume_block_comment()?,
+                _ => break Ok(()),
             }
         }
     }
@@ -93,100 +180,303 @@ impl<'a> Lexer<'a> {
     fn consume_identifier(&mut self) -> &'a str {
         let start = self.position;
         while let Some(c) = self.curr_char {
-            if c.is_alphanumeric() {
-                self.next_char();
-            } else {
-                return &self.input[start..self.position];
+            if !is_xid_continue(c) {
+                break;
             }
+            self.next_char();
         }
-        &self.input[start..=self.position]
+        &self.input[start..self.position]
     }
 
-    fn consume_number(&mut self) -> Result<Literal<'a>> {
-        let start = self.position;
-        enum States {
-            S1,
-            S2,
-            S3,
-        }
-        let mut state = States::S1;
+    /// reads digits of `radix`, dropping the `_` separators, which must sit between digits
+    fn consume_digits(&mut self, radix: u32) -> Result<String> {
+        let mut digits = String::new();
 
         while let Some(c) = self.curr_char {
-            match (&state, c) {
-                (States::S1, c) if c.is_ascii_digit() => {}
-                (States::S1, '.') => state = States::S2,
-                (States::S2, c) if c.is_ascii_digit() => state = States::S3,
-                (States::S3, c) if c.is_ascii_digit() => {}
+            match c {
+                c if c.is_digit(radix) => digits.push(c),
+                '_' if !digits.is_empty()
+                    && self.peek_char().is_some_and(|c| c.is_digit(radix)) => {}
+                '_' => return self.new_error("o separador _ deve ficar entre dois dígitos"),
                 _ => break,
             }
-
             self.next_char();
         }
 
-        let string = if let Some(..) = self.curr_char {
-            &self.input[start..self.position]
-        } else {
-            &self.input[start..=self.position]
+        Ok(digits)
+    }
+
+    /// a number can't be followed by a letter or a digit of another base, as in 12abc or 0b102
+    fn check_number_end(&self, kind: &str) -> Result<()> {
+        match self.curr_char {
+            Some(c) if is_xid_continue(c) => {
+                self.new_error(&format!("caracter {c} inválido em um número {kind}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn consume_number(&mut self) -> Result<Literal<'a>> {
+        let start = self.position;
+        let pos = self.get_pos();
+        let error = |msg: String| LexicalError {
+            row: pos.row,
+            col: pos.col,
+            msg,
+        };
+
+        // 0x and 0b prefixes, only for integers
+        let radix = match (self.curr_char, self.peek_char()) {
+            (Some('0'), Some('x' | 'X')) => Some((16, "hexadecimal")),
+            (Some('0'), Some('b' | 'B')) => Some((2, "binário")),
+            _ => None,
         };
 
-        match state {
-            States::S1 => {
-                let inteiro = string.parse().map_err(|err| LexicalError {
-                    row: self.line_num,
-                    col: start - self.line_start,
-                    msg: format!("{string} não pôde ser convertido para inteiro: {err}"),
-                })?;
-                Ok(Literal::Inteiro(inteiro))
+        if let Some((radix, kind)) = radix {
+            self.next_char();
+            self.next_char();
+
+            let digits = self.consume_digits(radix)?;
+            self.check_number_end(kind)?;
+            if digits.is_empty() {
+                return self.new_error(&format!("esperado dígito {kind} após o prefixo"));
+            }
+
+            let string = &self.input[start..self.position];
+            let inteiro = i64::from_str_radix(&digits, radix).map_err(|_| {
+                error(format!(
+                    "{string} é grande demais para um inteiro, o maior é {}",
+                    i64::MAX
+                ))
+            })?;
+            return Ok(Literal::Inteiro(inteiro));
+        }
+
+        let mut number = self.consume_digits(10)?;
+        let mut real = false;
+
+        if self.curr_char == Some('.') {
+            self.next_char();
+            if !self.curr_char.is_some_and(|c| c.is_ascii_digit()) {
+                return self.new_error("esperado dígito após o ponto, como em 1.0");
+            }
+            number.push('.');
+            number += &self.consume_digits(10)?;
+            real = true;
+        }
+
+        if let Some('e' | 'E') = self.curr_char {
+            self.next_char();
+            number.push('e');
+            if let Some(sign @ ('+' | '-')) = self.curr_char {
+                self.next_char();
+                number.push(sign);
+            }
+            if !self.curr_char.is_some_and(|c| c.is_ascii_digit()) {
+                return self.new_error("esperado dígito no expoente, como em 1e-6");
             }
-            States::S2 => Err(LexicalError {
-                row: self.line_num,
-                col: start - self.line_start,
-                msg: format!("esperado numero após ponto: {string}"),
-            }),
-            States::S3 => {
-                let decimal = string.parse().map_err(|err| LexicalError {
-                    row: self.line_num,
-                    col: start - self.line_start,
-                    msg: format!("{string} não pôde ser convertido para decimal: {err}"),
-                })?;
-                Ok(Literal::Decimal(decimal))
+            number += &self.consume_digits(10)?;
+            real = true;
+        }
+
+        self.check_number_end("decimal")?;
+        let string = &self.input[start..self.position];
+
+        if real {
+            match number.parse::<f64>() {
+                Ok(decimal) if decimal.is_finite() => Ok(Literal::Decimal(decimal)),
+                _ => Err(error(format!("{string} é grande demais para um real"))),
             }
+        } else {
+            let inteiro = number.parse().map_err(|_| {
+                error(format!(
+                    "{string} é grande demais para um inteiro, o maior é {}",
+                    i64::MAX
+                ))
+            })?;
+            Ok(Literal::Inteiro(inteiro))
         }
     }
 
-    fn consume_string(&mut self) -> Result<Literal<'a>> {
-        let mut state: u8 = 1;
+    /// reads an escape sequence from its backslash, giving the character it stands for
+    fn consume_escape(&mut self) -> Result<char> {
+        let pos = self.get_pos();
+        let error = |msg: String| LexicalError {
+            row: pos.row,
+            col: pos.col,
+            msg,
+        };
+
+        self.next_char(); // skip the backslash
+        let c = match self.curr_char {
+            Some(c) if c != '\n' && c != '\r' => c,
+            _ => return Err(error("sequência de escape incompleta".into())),
+        };
         self.next_char();
 
-        let start = self.position;
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            '"' => Ok('"'),
+            '\\' => Ok('\\'),
+            '{' => Ok('{'),
+            '}' => Ok('}'),
+            'u' => {
+                if self.curr_char != Some('{') {
+                    return self.new_error("esperado { após \\u, como em \\u{e9}");
+                }
+                self.next_char();
 
-        while let Some(c) = self.curr_char {
-            if c == '"' {
-                state = 2;
-                break;
+                let start = self.position;
+                while let Some(c) = self.curr_char {
+                    if !c.is_ascii_hexdigit() {
+                        break;
+                    }
+                    self.next_char();
+                }
+                let digits = &self.input[start..self.position];
+
+                if self.curr_char != Some('}') {
+                    return self.new_error("esperado dígito hexadecimal ou } no código unicode");
+                }
+                self.next_char();
+
+                if digits.is_empty() || digits.len() > 6 {
+                    return Err(error(
+                        "o código unicode deve ter de 1 a 6 dígitos hexadecimais".into(),
+                    ));
+                }
+
+                u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| error(format!("{digits} não é um código unicode válido")))
             }
+            c => Err(error(format!(
+                "sequência de escape inválida \\{c}, as válidas são \\n, \\t, \\\", \\\\, \\{{, \\}} e \\u{{...}}"
+            ))),
+        }
+    }
 
-            self.next_char();
+    /// reads the tokens of an expression embedded in a text, from its `{`,
+    /// ending with the token of the closing `}`
+    fn consume_interpolation(&mut self) -> Result<Vec<TokenDef<'a>>> {
+        let pos = self.get_pos();
+        self.next_char(); // skip the {
+
+        let mut tokens = Vec::new();
+        loop {
+            // a comment would take the rest of the text with it
+            self.consume_whitespace();
+            if let (Some('#'), _) | (Some('/'), Some('*')) = (self.curr_char, self.peek_char()) {
+                return self.new_error("comentários não podem ficar dentro de { e } num texto");
+            }
+
+            match self.curr_char {
+                Some('}') => {
+                    tokens.extend(self.next_token()?);
+                    break;
+                }
+                Some(_) => tokens.extend(self.next_token()?),
+                None => {
+                    return Err(LexicalError {
+                        row: pos.row,
+                        col: pos.col,
+                        msg: "{ sem } correspondente no texto, use \\{ para escrever uma chave"
+                            .into(),
+                    })
+                }
+            }
         }
 
-        if state != 2 {
-            return self.new_error("aspas (\") finais correspondentes não encontradas");
+        if tokens.len() == 1 {
+            return Err(LexicalError {
+                row: pos.row,
+                col: pos.col,
+                msg: "interpolação vazia, escreva uma expressão entre { e }".into(),
+            });
         }
 
-        let result = &self.input[start..self.position];
+        Ok(tokens)
+    }
+
+    /// reads a text literal, which can span more than one line. Its pieces are
+    /// borrowed from the input, unless they have escape sequences. Texts with
+    /// expressions between `{` and `}` become an interpolation
+    fn consume_string(&mut self) -> Result<Token<'a>> {
+        let quote = self.get_pos();
         self.next_char();
 
-        Ok(Literal::Texto(result))
+        let mut parts = Vec::new();
+        let mut start = self.position;
+        let mut text: Option<String> = None; // only created at the first escape
+
+        loop {
+            match self.curr_char {
+                None => {
+                    return Err(LexicalError {
+                        row: quote.row,
+                        col: quote.col,
+                        msg: "aspas (\") finais correspondentes não encontradas".into(),
+                    })
+                }
+                Some('"') => break,
+                Some('\\') => {
+                    let mut owned = match text.take() {
+                        Some(owned) => owned,
+                        None => self.input[start..self.position].to_string(),
+                    };
+                    owned.push(self.consume_escape()?);
+                    text = Some(owned);
+                }
+                Some('{') => {
+                    let src = &self.input[start..self.position];
+                    let piece = match text.take() {
+                        Some(owned) => Cow::Owned(owned),
+                        None => Cow::Borrowed(src),
+                    };
+                    if !piece.is_empty() {
+                        parts.push(Parte::Texto(piece, src));
+                    }
+                    parts.push(Parte::Expressao(self.consume_interpolation()?));
+                    start = self.position;
+                }
+                Some(c) => {
+                    let from = self.position;
+                    self.next_char();
+                    self.consume_line_break(c);
+                    if let Some(text) = &mut text {
+                        text.push_str(&self.input[from..self.position]);
+                    }
+                }
+            }
+        }
+
+        let src = &self.input[start..self.position];
+        let piece = match text {
+            Some(owned) => Cow::Owned(owned),
+            None => Cow::Borrowed(src),
+        };
+        self.next_char();
+
+        if parts.is_empty() {
+            return Ok(Token::Literal(Literal::Texto(piece)));
+        }
+        if !piece.is_empty() {
+            parts.push(Parte::Texto(piece, src));
+        }
+        Ok(Token::Interpolacao(parts))
     }
 
     fn next_token(&mut self) -> Result<Option<TokenDef<'a>>> {
-        self.consume_whitespace();
+        self.consume_trivia()?;
 
         let Some(c) = self.curr_char else {
             return Ok(None);
         };
 
         let pos = self.get_pos();
+        let start = self.position;
 
         match c {
             '0'..='9' => {
@@ -196,6 +486,8 @@ impl<'a> Lexer<'a> {
                 Ok(Some(TokenDef {
                     tok: Token::Literal(literal),
                     pos,
+                    end: self.get_pos(),
+                    src: &self.input[start..self.position],
                 }))
             }
             '<' | '>' | '=' | '+' | '-' | '*' | '/' | '%' | '^' | ':' => {
@@ -251,19 +543,31 @@ impl<'a> Lexer<'a> {
                     ('/', _) => Operador::Div,
                     ('%', _) => Operador::Resto,
                     ('^', _) => Operador::Exp,
+                    (':', _) => {
+                        return Ok(Some(TokenDef {
+                            tok: Token::Delimitador(Delimitador::DoisPontos),
+                            pos,
+                            end: self.get_pos(),
+                            src: &self.input[start..self.position],
+                        }))
+                    }
                     _ => self.new_error("operador inválido")?,
                 };
 
                 Ok(Some(TokenDef {
                     tok: Token::Operador(operador),
                     pos,
+                    end: self.get_pos(),
+                    src: &self.input[start..self.position],
                 }))
             }
             '"' => {
-                let val = self.consume_string()?;
+                let tok = self.consume_string()?;
                 Ok(Some(TokenDef {
-                    tok: Token::Literal(val),
+                    tok,
                     pos,
+                    end: self.get_pos(),
+                    src: &self.input[start..self.position],
                 }))
             }
             '(' | ')' | '{' | '}' | '[' | ']' | ',' | ';' => {
@@ -283,9 +587,11 @@ impl<'a> Lexer<'a> {
                 Ok(Some(TokenDef {
                     tok: Token::Delimitador(del),
                     pos,
+                    end: self.get_pos(),
+                    src: &self.input[start..self.position],
                 }))
             }
-            'a'..='z' | 'A'..='Z' => {
+            c if c == '_' || is_xid_start(c) => {
                 let identifier = self.consume_identifier();
                 let tok = match identifier {
                     "programa" => Token::Programa,
@@ -294,26 +600,41 @@ impl<'a> Lexer<'a> {
                     "real" => Token::Real,
                     "texto" => Token::Texto,
                     "booleano" => Token::Booleano,
+                    "lista" => Token::Lista,
+                    "de" => Token::De,
                     "repetir" => Token::Repetir,
-                    "entao" => Token::Entao,
+                    "pare" => Token::Pare,
+                    "continue" => Token::Continue,
+                    // keywords with accents can be written without them too
+                    "entao" | "então" => Token::Entao,
+                    "senao" | "senão" => Token::Senao,
                     "enquanto" => Token::Enquanto,
                     "se" => Token::Se,
-                    "função" => Token::Funcao,
+                    "funcao" | "função" => Token::Funcao,
                     "para" => Token::Para,
                     "incremento" => Token::Incremento,
                     "retorne" => Token::Retorne,
                     "fim" => Token::Fim,
                     "e" => Token::Operador(Operador::E),
                     "ou" => Token::Operador(Operador::Ou),
-                    "ate" => Token::Ate,
+                    "nao" | "não" => Token::Operador(Operador::Nao),
+                    "ate" | "até" => Token::Ate,
                     "verdadeiro" => Token::Literal(Literal::Booleano(true)),
                     "falso" => Token::Literal(Literal::Booleano(false)),
                     _ => Token::Identificador(identifier),
                 };
 
-                Ok(Some(TokenDef { tok, pos }))
+                Ok(Some(TokenDef {
+                    tok,
+                    pos,
+                    end: self.get_pos(),
+                    src: &self.input[start..self.position],
+                }))
             }
 
+            '.' if self.peek_char().is_some_and(|c| c.is_ascii_digit()) => {
+                self.new_error("um número real deve começar com um dígito, como em 0.5")
+            }
             _ => self.new_error("caracter não esperado"),
         }
     }
@@ -325,8 +646,203 @@ impl<'a> Lexer<'a> {
         }
         Ok(tokens)
     }
+
+    pub fn comments(&self) -> &[Comment<'a>] {
+        &self.comments
+    }
 }
 
-pub fn lex(code: &str) -> Result<Vec<TokenDef>> {
+pub fn lex(code: &str) -> Result<Vec<TokenDef<'_>>> {
     Lexer::new(code).tokenize()
 }
+
+/// like [`lex`], also returning the comments found in the code
+pub fn lex_with_comments(code: &str) -> Result<(Vec<TokenDef<'_>>, Vec<Comment<'_>>)> {
+    let mut lexer = Lexer::new(code);
+    let tokens = lexer.tokenize()?;
+    Ok((tokens, lexer.comments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(code: &str) -> Vec<Token<'_>> {
+        lex(code)
+            .unwrap_or_else(|err| panic!("{}", err.msg))
+            .into_iter()
+            .map(|def| def.tok)
+            .collect()
+    }
+
+    #[test]
+    fn keywords_with_and_without_accents() {
+        assert_eq!(
+            tokens("então entao senão senao não nao até ate função funcao"),
+            [
+                Token::Entao,
+                Token::Entao,
+                Token::Senao,
+                Token::Senao,
+                Token::Operador(Operador::Nao),
+                Token::Operador(Operador::Nao),
+                Token::Ate,
+                Token::Ate,
+                Token::Funcao,
+                Token::Funcao,
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        assert_eq!(
+            tokens("área ação _x x_1 变量 fimx"),
+            [
+                Token::Identificador("área"),
+                Token::Identificador("ação"),
+                Token::Identificador("_x"),
+                Token::Identificador("x_1"),
+                Token::Identificador("变量"),
+                Token::Identificador("fimx"),
+            ]
+        );
+
+        let err = lex("inteiro a := 1\nsaida(a @ 2)").unwrap_err();
+        assert_eq!((err.row, err.col), (2, 8));
+        assert_eq!(err.msg, "caracter não esperado");
+    }
+
+    /// the error of lexing `code`, with its position
+    fn error(code: &str) -> (usize, usize, String) {
+        let err = lex(code).expect_err("code should be refused");
+        (err.row, err.col, err.msg)
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(
+            tokens(r#""a\nb\t\"c\" \\ \{d\} \u{e9}\u{1F600}""#),
+            [Token::Literal(Literal::Texto(
+                "a\nb\t\"c\" \\ {d} é😀".into()
+            ))]
+        );
+
+        // texts without escapes borrow from the code, line breaks included
+        let [Token::Literal(Literal::Texto(text))] = &tokens("\"uma\nduas\"")[..] else {
+            panic!("expected a text");
+        };
+        assert!(matches!(text, Cow::Borrowed("uma\nduas")));
+    }
+
+    #[test]
+    fn bad_escape_sequences() {
+        assert_eq!(
+            error(r#""ab\q""#),
+            (
+                1,
+                3,
+                r#"sequência de escape inválida \q, as válidas são \n, \t, \", \\, \{, \} e \u{...}"#
+                    .into()
+            )
+        );
+        assert_eq!(
+            error("\"a\\\nb\""),
+            (1, 2, "sequência de escape incompleta".into())
+        );
+        assert_eq!(
+            error(r#""\u41""#),
+            (1, 3, "esperado { após \\u, como em \\u{e9}".into())
+        );
+        assert_eq!(
+            error(r#""\u{4g}""#),
+            (
+                1,
+                5,
+                "esperado dígito hexadecimal ou } no código unicode".into()
+            )
+        );
+        assert_eq!(
+            error(r#""\u{}""#),
+            (
+                1,
+                1,
+                "o código unicode deve ter de 1 a 6 dígitos hexadecimais".into()
+            )
+        );
+        assert_eq!(
+            error(r#""\u{D800}""#),
+            (1, 1, "D800 não é um código unicode válido".into())
+        );
+        assert_eq!(
+            error("x := \"sem fim\nsaida(x)"),
+            (
+                1,
+                5,
+                "aspas (\") finais correspondentes não encontradas".into()
+            )
+        );
+    }
+
+    #[test]
+    fn no_comments_in_interpolations() {
+        let msg = "comentários não podem ficar dentro de { e } num texto";
+        assert_eq!(error(r#"saida("v={a # c}")"#), (1, 12, msg.into()));
+        assert_eq!(error(r#"saida("w={a /* c */ + 1}")"#), (1, 12, msg.into()));
+
+        // the same characters are text outside the braces
+        assert_eq!(tokens(r##""# /* {a}""##).len(), 1);
+    }
+
+    #[test]
+    fn numbers() {
+        let number = |code| match &tokens(code)[..] {
+            [Token::Literal(literal)] => literal.clone(),
+            tokens => panic!("{code} is {tokens:?}"),
+        };
+
+        assert_eq!(number("42"), Literal::Inteiro(42));
+        assert_eq!(number("1_000_000"), Literal::Inteiro(1_000_000));
+        assert_eq!(number("0xFF"), Literal::Inteiro(255));
+        assert_eq!(number("0Xff_ff"), Literal::Inteiro(0xffff));
+        assert_eq!(number("0b1010"), Literal::Inteiro(10));
+        assert_eq!(number("9223372036854775807"), Literal::Inteiro(i64::MAX));
+        assert_eq!(number("1.5"), Literal::Decimal(1.5));
+        assert_eq!(number("1e-6"), Literal::Decimal(1e-6));
+        assert_eq!(number("6.02e23"), Literal::Decimal(6.02e23));
+        assert_eq!(number("2E+3"), Literal::Decimal(2000.0));
+    }
+
+    #[test]
+    fn bad_numbers_point_at_the_bad_digit() {
+        let cases = [
+            ("1__0", 1, "o separador _ deve ficar entre dois dígitos"),
+            ("1_", 1, "o separador _ deve ficar entre dois dígitos"),
+            ("0b102", 4, "caracter 2 inválido em um número binário"),
+            ("0xG", 2, "caracter G inválido em um número hexadecimal"),
+            ("12abc", 2, "caracter a inválido em um número decimal"),
+            ("0b", 2, "esperado dígito binário após o prefixo"),
+            (".5", 0, "um número real deve começar com um dígito, como em 0.5"),
+            ("1.", 2, "esperado dígito após o ponto, como em 1.0"),
+            ("1e+", 3, "esperado dígito no expoente, como em 1e-6"),
+            ("1e999", 0, "1e999 é grande demais para um real"),
+            (
+                "9223372036854775808",
+                0,
+                "9223372036854775808 é grande demais para um inteiro, o maior é 9223372036854775807",
+            ),
+            (
+                "0x8000000000000000",
+                0,
+                "0x8000000000000000 é grande demais para um inteiro, o maior é 9223372036854775807",
+            ),
+        ];
+        for (code, col, msg) in cases {
+            assert_eq!(
+                error(&format!("x := {code}")),
+                (1, col + 5, msg.into()),
+                "{code}"
+            );
+        }
+    }
+}
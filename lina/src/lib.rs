@@ -1,6 +1,12 @@
 pub mod compiler;
+pub mod diagnostic;
+pub mod formatter;
 pub mod lexer;
+pub mod linac;
+pub mod optimizer;
 pub mod parser;
+pub mod repl;
 pub mod syntax;
 pub mod token;
+pub mod verifier;
 pub mod vm;
@@ -0,0 +1,273 @@
+//! The `.linac` file, a compiled program that runs without its source.
+//!
+//! ```text
+//! magic      4 bytes   \x7fLNC
+//! version    u16
+//! sections   id: u8, length: u32, then `length` bytes
+//!   1 constants   count: u32, then each constant as a tag: u8 and its value
+//!   2 code        the bytecode, its operands already little-endian
+//!   3 debug       count: u32, then offset, row and col: u32 of each statement
+//! ```
+//!
+//! Every number is little-endian. The constants and the code are required,
+//! without the debug section runtime errors have no position in the source.
+
+use std::fmt::Display;
+
+use crate::compiler::ByteCode;
+use crate::token::TokenPos;
+use crate::vm::LinaValue;
+
+pub const MAGIC: [u8; 4] = *b"\x7fLNC";
+pub const VERSION: u16 = 2; // 2: operands of one or two bytes, WIDE for four
+
+const CONSTANTS: u8 = 1;
+const CODE: u8 = 2;
+const DEBUG: u8 = 3;
+
+const INT64: u8 = 0;
+const FLOAT64: u8 = 1;
+const STRING: u8 = 2;
+const BOOLEAN: u8 = 3;
+const ADDRESS: u8 = 4;
+
+#[derive(Debug)]
+pub struct LinacError {
+    pub offset: usize, // byte of the file where the problem was found
+    pub msg: String,
+}
+
+impl Display for LinacError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (byte {})", self.msg, self.offset)
+    }
+}
+
+type Result<T> = std::result::Result<T, LinacError>;
+
+/// whether `bytes` start like a `.linac` file, rather than like source code
+pub fn is_linac(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// the file for `code`, with the statement positions when `debug` is set
+pub fn encode(code: &ByteCode, debug: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend(MAGIC);
+    out.extend(VERSION.to_le_bytes());
+
+    let mut constants = Vec::new();
+    put_u32(&mut constants, code.constants.len());
+    for constant in &code.constants {
+        put_constant(&mut constants, constant);
+    }
+    put_section(&mut out, CONSTANTS, &constants);
+
+    put_section(&mut out, CODE, &code.bytecode);
+
+    if debug {
+        let mut lines = Vec::new();
+        put_u32(&mut lines, code.lines.len());
+        for (offset, pos) in &code.lines {
+            put_u32(&mut lines, *offset);
+            put_u32(&mut lines, pos.row);
+            put_u32(&mut lines, pos.col);
+        }
+        put_section(&mut out, DEBUG, &lines);
+    }
+
+    out
+}
+
+fn put_u32(out: &mut Vec<u8>, value: usize) {
+    let value = u32::try_from(value).expect("value should fit in 32 bits");
+    out.extend(value.to_le_bytes());
+}
+
+fn put_section(out: &mut Vec<u8>, id: u8, payload: &[u8]) {
+    out.push(id);
+    put_u32(out, payload.len());
+    out.extend(payload);
+}
+
+fn put_constant(out: &mut Vec<u8>, constant: &LinaValue) {
+    match constant {
+        LinaValue::Int64(value) => {
+            out.push(INT64);
+            out.extend(value.to_le_bytes());
+        }
+        LinaValue::Float64(value) => {
+            out.push(FLOAT64);
+            out.extend(value.to_le_bytes());
+        }
+        LinaValue::String(value) => {
+            out.push(STRING);
+            put_u32(out, value.len());
+            out.extend(value.as_bytes());
+        }
+        LinaValue::Boolean(value) => {
+            out.push(BOOLEAN);
+            out.push(*value as u8);
+        }
+        LinaValue::Address(value) => {
+            out.push(ADDRESS);
+            put_u32(out, *value);
+        }
+        LinaValue::List(..) => unreachable!("lists are built while running, never constants"),
+    }
+}
+
+/// reads a file written by `encode`
+pub fn decode(bytes: &[u8]) -> Result<ByteCode> {
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        end: bytes.len(),
+    };
+
+    if !is_linac(bytes) {
+        return reader.error(0, "o arquivo não é um programa compilado da lina");
+    }
+    reader.pos = MAGIC.len();
+
+    let version = reader.u16()?;
+    if version != VERSION {
+        return reader.error(
+            MAGIC.len(),
+            format!("versão {version} do formato não suportada, a versão atual é {VERSION}"),
+        );
+    }
+
+    let mut constants = None;
+    let mut bytecode = None;
+    let mut lines = None;
+
+    while reader.pos < bytes.len() {
+        let start = reader.pos;
+        let id = reader.u8()?;
+        let len = reader.u32()?;
+        if len > bytes.len() - reader.pos {
+            return reader.error(start, "a seção passa do fim do arquivo");
+        }
+        reader.end = reader.pos + len;
+
+        match id {
+            CONSTANTS if constants.is_none() => constants = Some(reader.constants()?),
+            CODE if bytecode.is_none() => bytecode = Some(reader.take(len)?.to_vec()),
+            DEBUG if lines.is_none() => lines = Some(reader.lines()?),
+            CONSTANTS | CODE | DEBUG => return reader.error(start, "seção repetida"),
+            _ => return reader.error(start, format!("seção desconhecida: {id}")),
+        }
+
+        if reader.pos != reader.end {
+            return reader.error(reader.pos, "sobram bytes no fim da seção");
+        }
+        reader.end = bytes.len();
+    }
+
+    let Some(constants) = constants else {
+        return reader.error(bytes.len(), "falta a seção de constantes");
+    };
+    let Some(bytecode) = bytecode.filter(|code| !code.is_empty()) else {
+        return reader.error(bytes.len(), "falta a seção de código");
+    };
+
+    Ok(ByteCode {
+        bytecode,
+        constants,
+        lines: lines.unwrap_or_default(),
+    })
+}
+
+/// reads the file up to `end`, the end of the current section
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error<T>(&self, offset: usize, msg: impl Into<String>) -> Result<T> {
+        Err(LinacError {
+            offset,
+            msg: msg.into(),
+        })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.end - self.pos {
+            let msg = if self.end == self.bytes.len() {
+                "o arquivo termina antes do esperado"
+            } else {
+                "a seção termina antes do esperado"
+            };
+            return self.error(self.end, msg);
+        }
+
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self.take(N)?;
+        Ok(bytes.try_into().expect("take returns the requested length"))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<usize> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn constants(&mut self) -> Result<Vec<LinaValue>> {
+        let count = self.u32()?;
+        let mut constants = Vec::new();
+        for _ in 0..count {
+            constants.push(self.constant()?);
+        }
+        Ok(constants)
+    }
+
+    fn constant(&mut self) -> Result<LinaValue> {
+        let start = self.pos;
+        let value = match self.u8()? {
+            INT64 => LinaValue::Int64(i64::from_le_bytes(self.array()?)),
+            FLOAT64 => LinaValue::Float64(f64::from_le_bytes(self.array()?)),
+            STRING => {
+                let len = self.u32()?;
+                let text = self.take(len)?;
+                match std::str::from_utf8(text) {
+                    Ok(text) => LinaValue::String(text.into()),
+                    Err(_) => return self.error(start, "texto com UTF-8 inválido"),
+                }
+            }
+            BOOLEAN => match self.u8()? {
+                0 => LinaValue::Boolean(false),
+                1 => LinaValue::Boolean(true),
+                byte => return self.error(start + 1, format!("booleano inválido: {byte}")),
+            },
+            ADDRESS => LinaValue::Address(self.u32()?),
+            tag => return self.error(start, format!("tipo de constante desconhecido: {tag}")),
+        };
+        Ok(value)
+    }
+
+    fn lines(&mut self) -> Result<Vec<(usize, TokenPos)>> {
+        let count = self.u32()?;
+        let mut lines = Vec::new();
+        for _ in 0..count {
+            let offset = self.u32()?;
+            let row = self.u32()?;
+            let col = self.u32()?;
+            lines.push((offset, TokenPos { row, col }));
+        }
+        Ok(lines)
+    }
+}
@@ -1,45 +1,319 @@
 use std::env;
 use std::fs;
-use std::io::{stdin, stdout};
+use std::io::{stdin, stdout, Stdin, Stdout, Write};
+use std::path::Path;
+use std::process::ExitCode;
 
-use lina::compiler::compile;
-use lina::lexer::lex;
+use lina::compiler::{compile, ByteCode};
+use lina::diagnostic::Diagnostic;
+use lina::formatter::format;
+use lina::lexer::{lex, lex_with_comments, LexicalError};
+use lina::linac::{decode, encode, is_linac};
+use lina::optimizer::optimize;
 use lina::parser::parse;
+use lina::repl::{is_incomplete, Repl, ReplError};
+use lina::syntax::Program;
+use lina::token::TokenDef;
 use lina::vm::LinaVm;
 
-fn main() -> std::result::Result<(), ()> {
-    let args = env::args().collect::<Vec<_>>();
-    let file_path = args.get(1).ok_or(()).map_err(|_| {
-        eprintln!("Erro: arquivo não fornecido");
-    })?;
+const AJUDA: &str = "\
+uso: lina [-O] [comando] [arquivo]
+
+comandos:
+    executar <arquivo>    executa o programa, o mesmo que `lina <arquivo>`,
+                          o arquivo pode ser o código ou o programa compilado
+    compilar <arquivo>    compila o programa para <arquivo>.linac
+    compilar --sem-depuracao <arquivo>
+                          compila sem as posições usadas nas mensagens de erro
+    verificar <arquivo>   verifica os erros do programa sem executá-lo
+    desmontar <arquivo>   mostra o bytecode gerado para o programa
+    tokens <arquivo>      mostra os tokens lidos do programa
+    arvore <arquivo>      mostra a árvore sintática do programa
+    formatar <arquivo>    reescreve o arquivo no formato padrão
+    formatar --verificar <arquivo>
+                          falha se o arquivo não estiver no formato padrão
+    ajuda                 mostra esta mensagem
+
+opções:
+    -O                    otimiza o programa antes de compilar: calcula as operações
+                          entre valores fixos e remove os blocos que nunca executam
+
+sem nenhum argumento, abre o modo interativo.
+
+códigos de saída:
+    0    sucesso
+    1    erro durante a execução do programa
+    2    uso incorreto da linha de comando
+    3    não foi possivel ler ou escrever o arquivo
+    4    erro léxico
+    5    erro sintático
+    6    erro de compilação
+    7    arquivo fora do formato padrão
+    8    programa compilado inválido";
+
+/// exit codes of the process, listed in `AJUDA`
+#[derive(Clone, Copy)]
+enum Falha {
+    Execucao = 1,
+    Uso = 2,
+    Arquivo = 3,
+    Lexica = 4,
+    Sintatica = 5,
+    Compilacao = 6,
+    Formato = 7,
+    Modulo = 8,
+}
+
+impl From<Falha> for ExitCode {
+    fn from(value: Falha) -> Self {
+        ExitCode::from(value as u8)
+    }
+}
+
+type Result<T> = std::result::Result<T, Falha>;
 
-    let code = fs::read_to_string(file_path).map_err(|err| {
+fn read(file_path: &str) -> Result<String> {
+    fs::read_to_string(file_path).map_err(|err| {
         eprintln!("Erro: não foi possivel ler o arquivo {file_path}: {err}");
-    })?;
+        Falha::Arquivo
+    })
+}
 
-    let tokens = lex(&code).map_err(|err| {
-        eprintln!("Erro Léxico: {}", err.msg);
-        eprintln!("\t--> em {}:{}:{}", file_path, err.row, err.col);
-    })?;
+/// prints `diagnostic` with the lines of `code` it points at
+fn report(file_path: &str, code: &str, diagnostic: Diagnostic) {
+    eprintln!("{}", diagnostic.render(file_path, code));
+}
 
-    let program = parse(tokens).map_err(|err| {
-        eprintln!("Erro Sintático: {}", err.msg);
-        eprintln!("\t--> em {}:{}:{}", file_path, err.pos.row, err.pos.col);
-    })?;
+fn lex_err(file_path: &str, code: &str, err: LexicalError) -> Falha {
+    report(file_path, code, Diagnostic::from(&err));
+    Falha::Lexica
+}
 
-    let mut vm = LinaVm::new(compile(&program), stdin(), stdout());
+fn tokenize<'a>(file_path: &str, code: &'a str) -> Result<Vec<TokenDef<'a>>> {
+    lex(code).map_err(|err| lex_err(file_path, code, err))
+}
 
-    if let Some(arg) = args.get(2) {
-        if arg == "-d" {
-            vm.decompile().map_err(|err| {
-                eprintln!("Erro: {err}");
-            })?;
+fn analyze<'a>(file_path: &str, code: &'a str) -> Result<Program<'a>> {
+    let tokens = tokenize(file_path, code)?;
+    syntax(file_path, code, tokens)
+}
+
+fn syntax<'a>(file_path: &str, code: &str, tokens: Vec<TokenDef<'a>>) -> Result<Program<'a>> {
+    parse(tokens).map_err(|err| {
+        for err in err.errors.iter() {
+            report(file_path, code, Diagnostic::from(err));
         }
-    } else {
-        vm.run().map_err(|err| {
-            eprintln!("Erro: {err}");
+        Falha::Sintatica
+    })
+}
+
+/// the syntax tree of `code`, optimized when `optimized` is set
+fn program<'a>(file_path: &str, code: &'a str, optimized: bool) -> Result<Program<'a>> {
+    let mut program = analyze(file_path, code)?;
+    if optimized {
+        optimize(&mut program).map_err(|err| {
+            report(file_path, code, Diagnostic::from(&err));
+            Falha::Compilacao
+        })?;
+    }
+    Ok(program)
+}
+
+fn build(file_path: &str, code: &str, optimized: bool) -> Result<ByteCode> {
+    let program = program(file_path, code, optimized)?;
+    compile(&program).map_err(|err| {
+        report(file_path, code, Diagnostic::from(&err));
+        Falha::Compilacao
+    })
+}
+
+/// the bytecode of a source file or of a file written by `compilar`,
+/// with the source to show in the errors, empty for the compiled one
+fn load(file_path: &str, optimized: bool) -> Result<(ByteCode, String)> {
+    let bytes = fs::read(file_path).map_err(|err| {
+        eprintln!("Erro: não foi possivel ler o arquivo {file_path}: {err}");
+        Falha::Arquivo
+    })?;
+
+    if is_linac(&bytes) {
+        let bytecode = decode(&bytes).map_err(|err| {
+            eprintln!("Erro: {file_path} não é um programa compilado válido: {err}");
+            Falha::Modulo
         })?;
+        return Ok((bytecode, String::new()));
     }
 
+    let code = String::from_utf8(bytes).map_err(|_| {
+        eprintln!("Erro: o arquivo {file_path} não é um texto UTF-8");
+        Falha::Arquivo
+    })?;
+    Ok((build(file_path, &code, optimized)?, code))
+}
+
+fn start(file_path: &str, bytecode: ByteCode) -> Result<LinaVm<Stdin, Stdout>> {
+    LinaVm::new(bytecode, stdin(), stdout()).map_err(|err| {
+        eprintln!("Erro: {file_path} não é um programa compilado válido: {err}");
+        Falha::Modulo
+    })
+}
+
+fn executar(file_path: &str, optimized: bool) -> Result<()> {
+    let (bytecode, code) = load(file_path, optimized)?;
+    let mut vm = start(file_path, bytecode)?;
+
+    vm.run().map_err(|err| {
+        report(file_path, &code, Diagnostic::from(&err));
+        Falha::Execucao
+    })
+}
+
+fn verificar(file_path: &str, optimized: bool) -> Result<()> {
+    let code = read(file_path)?;
+    build(file_path, &code, optimized)?;
+    println!("{file_path}: nenhum erro encontrado");
     Ok(())
 }
+
+fn compilar(file_path: &str, debug: bool, optimized: bool) -> Result<()> {
+    let code = read(file_path)?;
+    let bytecode = build(file_path, &code, optimized)?;
+
+    let out_path = Path::new(file_path).with_extension("linac");
+    fs::write(&out_path, encode(&bytecode, debug)).map_err(|err| {
+        eprintln!(
+            "Erro: não foi possivel escrever o arquivo {}: {err}",
+            out_path.display()
+        );
+        Falha::Arquivo
+    })
+}
+
+fn desmontar(file_path: &str, optimized: bool) -> Result<()> {
+    let (bytecode, _) = load(file_path, optimized)?;
+    let mut vm = start(file_path, bytecode)?;
+
+    vm.decompile().map_err(|err| {
+        eprintln!("Erro: {err}");
+        Falha::Execucao
+    })
+}
+
+fn tokens(file_path: &str) -> Result<()> {
+    let code = read(file_path)?;
+    for TokenDef { tok, pos, .. } in tokenize(file_path, &code)? {
+        println!("{}:{}\t{tok}", pos.row, pos.col);
+    }
+    Ok(())
+}
+
+fn arvore(file_path: &str, optimized: bool) -> Result<()> {
+    let code = read(file_path)?;
+    print!("{}", program(file_path, &code, optimized)?);
+    Ok(())
+}
+
+fn formatar(file_path: &str, check: bool) -> Result<()> {
+    let code = read(file_path)?;
+    let (tokens, comments) =
+        lex_with_comments(&code).map_err(|err| lex_err(file_path, &code, err))?;
+    let program = syntax(file_path, &code, tokens)?;
+    let formatted = format(&program, &code, comments);
+
+    if formatted == code {
+        return Ok(());
+    }
+
+    if check {
+        eprintln!("{file_path}: fora do formato padrão, use `lina formatar {file_path}`");
+        return Err(Falha::Formato);
+    }
+
+    fs::write(file_path, formatted).map_err(|err| {
+        eprintln!("Erro: não foi possivel escrever o arquivo {file_path}: {err}");
+        Falha::Arquivo
+    })
+}
+
+fn uso() -> Result<()> {
+    eprintln!("{AJUDA}");
+    Err(Falha::Uso)
+}
+
+fn repl() -> Result<()> {
+    println!(
+        "Lina {} - modo interativo, digite `sair` para terminar",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let mut repl = Repl::new(stdin(), stdout());
+    let mut code = String::new();
+
+    loop {
+        // blocks keep reading lines until their fim
+        print!("{}", if code.is_empty() { ">> " } else { ".. " });
+        _ = stdout().flush();
+
+        let mut line = String::new();
+        let read = stdin().read_line(&mut line).map_err(|err| {
+            eprintln!("Erro: não foi possivel ler a entrada: {err}");
+            Falha::Execucao
+        })?;
+        if read == 0 || (code.is_empty() && line.trim() == "sair") {
+            break Ok(());
+        }
+
+        code.push_str(&line);
+        if is_incomplete(&code) {
+            continue;
+        }
+
+        let input = std::mem::take(&mut code);
+        match repl.eval(input.clone()) {
+            Ok(()) => {}
+            Err(ReplError::LexicalError(err)) => report("entrada", &input, Diagnostic::from(&err)),
+            Err(ReplError::SyntaxError(errors)) => {
+                for err in errors.iter() {
+                    report("entrada", &input, Diagnostic::from(err));
+                }
+            }
+            Err(ReplError::CompileError(err)) => report("entrada", &input, Diagnostic::from(&err)),
+            Err(ReplError::RuntimeError(err)) => report("entrada", &input, Diagnostic::from(&err)),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    // -O may come anywhere, the commands that do not compile ignore it
+    let optimized = args.contains(&"-O");
+    args.retain(|arg| *arg != "-O");
+
+    let result = match args.as_slice() {
+        [] => repl(),
+        ["ajuda"] => {
+            println!("{AJUDA}");
+            Ok(())
+        }
+        ["executar", file_path] => executar(file_path, optimized),
+        ["verificar", file_path] => verificar(file_path, optimized),
+        ["compilar", file_path] => compilar(file_path, true, optimized),
+        ["compilar", "--sem-depuracao", file_path] => compilar(file_path, false, optimized),
+        ["desmontar", file_path] => desmontar(file_path, optimized),
+        ["tokens", file_path] => tokens(file_path),
+        ["arvore", file_path] => arvore(file_path, optimized),
+        ["formatar", file_path] => formatar(file_path, false),
+        ["formatar", "--verificar", file_path] => formatar(file_path, true),
+        ["executar" | "verificar" | "compilar" | "desmontar" | "tokens" | "arvore" | "formatar"] => {
+            uso()
+        }
+        [file_path] => executar(file_path, optimized),
+        _ => uso(),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(falha) => falha.into(),
+    }
+}
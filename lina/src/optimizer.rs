@@ -0,0 +1,278 @@
+//! Rewrites the syntax tree between `parse` and `compile`, enabled by `-O`.
+//!
+//! Operations and casts whose operands are all literals are replaced by their
+//! result, so `0.0 - 1.0` compiles to a single constant. Branches of `se` whose
+//! condition folds to `falso`, and `enquanto` loops that never start, are removed
+//! along with their bodies. An operation that would fail when run, like a
+//! division by zero, is reported as a compilation error at its statement.
+
+use std::borrow::Cow;
+use std::io::{empty, sink};
+
+use crate::compiler::{compile_constant, CompileError};
+use crate::syntax::{Block, Expression, Program, SyntaxTree};
+use crate::token::{Literal, TokenPos};
+use crate::vm::{LinaValue, LinaVm};
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+pub fn optimize(program: &mut Program) -> Result<()> {
+    optimize_block(&mut program.block)
+}
+
+fn optimize_block(blk: &mut Block) -> Result<()> {
+    for stmt in blk.take_stmts() {
+        optimize_stmt(stmt, blk)?;
+    }
+    Ok(())
+}
+
+/// pushes what is left of `stmt` into `out`, which may be nothing or many statements
+fn optimize_stmt<'a>(stmt: SyntaxTree<'a>, out: &mut Block<'a>) -> Result<()> {
+    let stmt = match stmt {
+        SyntaxTree::Assign {
+            pos,
+            dcl,
+            typ,
+            idt,
+            mut exp,
+        } => {
+            fold(&mut exp, &pos)?;
+            SyntaxTree::Assign {
+                pos,
+                dcl,
+                typ,
+                idt,
+                exp,
+            }
+        }
+        SyntaxTree::Expr { pos, mut exp } => {
+            fold(&mut exp, &pos)?;
+            SyntaxTree::Expr { pos, exp }
+        }
+        SyntaxTree::SeStmt {
+            pos,
+            exp,
+            blk,
+            alt,
+            els,
+        } => return optimize_se(pos, exp, blk, alt, els, out),
+        SyntaxTree::EnquantoStmt {
+            pos,
+            mut exp,
+            mut blk,
+        } => {
+            fold(&mut exp, &pos)?;
+            if is_bool(&exp, false) {
+                return Ok(());
+            }
+            optimize_block(&mut blk)?;
+            SyntaxTree::EnquantoStmt { pos, exp, blk }
+        }
+        SyntaxTree::ParaStmt {
+            pos,
+            idt,
+            sta,
+            mut lmt,
+            stp,
+            mut blk,
+        } => {
+            fold(&mut lmt, &pos)?;
+            optimize_block(&mut blk)?;
+            SyntaxTree::ParaStmt {
+                pos,
+                idt,
+                sta,
+                lmt,
+                stp,
+                blk,
+            }
+        }
+        SyntaxTree::FuncaoStmt {
+            pos,
+            idt,
+            par,
+            ret,
+            mut blk,
+        } => {
+            optimize_block(&mut blk)?;
+            SyntaxTree::FuncaoStmt {
+                pos,
+                idt,
+                par,
+                ret,
+                blk,
+            }
+        }
+        SyntaxTree::RetorneStmt { pos, mut exp } => {
+            if let Some(exp) = &mut exp {
+                fold(exp, &pos)?;
+            }
+            SyntaxTree::RetorneStmt { pos, exp }
+        }
+        stmt @ (SyntaxTree::PareStmt { .. } | SyntaxTree::ContinueStmt { .. }) => stmt,
+    };
+
+    out.push_stmt(stmt);
+    Ok(())
+}
+
+/// keeps the branches whose condition is not known, up to the first one that
+/// is always taken, which becomes the senao
+fn optimize_se<'a>(
+    pos: TokenPos,
+    exp: Expression<'a>,
+    blk: Block<'a>,
+    alt: Vec<(Expression<'a>, Block<'a>)>,
+    mut els: Option<Block<'a>>,
+    out: &mut Block<'a>,
+) -> Result<()> {
+    let mut branches = Vec::new();
+    let mut dead = None; // the last branch never taken
+    for (mut exp, mut blk) in std::iter::once((exp, blk)).chain(alt) {
+        fold(&mut exp, &pos)?;
+        if is_bool(&exp, false) {
+            dead = Some((exp, blk));
+            continue;
+        }
+
+        optimize_block(&mut blk)?;
+        if !is_bool(&exp, true) {
+            branches.push((exp, blk));
+            continue;
+        }
+
+        // a block with declarations keeps a se of its own, so its variables stay
+        // in their scope. When it returns, the senao stays too, since the parser
+        // may have counted on both returning to accept the function
+        if branches.is_empty() && declares(&blk) {
+            let els = els.filter(|_| blk.returns());
+            let stmt = SyntaxTree::SeStmt {
+                pos,
+                exp,
+                blk,
+                alt: Vec::new(),
+                els,
+            };
+            out.push_stmt(stmt);
+            return Ok(());
+        }
+        els = Some(blk);
+        break;
+    }
+
+    if let Some(els) = &mut els {
+        optimize_block(els)?;
+    }
+
+    let mut branches = branches.into_iter();
+    let Some((exp, blk)) = branches.next() else {
+        // only the senao may run, and it always does
+        if let Some(mut els) = els {
+            if declares(&els) {
+                // as above, with a branch never taken in front of the senao when it returns
+                let stmt = match dead.filter(|_| els.returns()) {
+                    Some((exp, blk)) => SyntaxTree::SeStmt {
+                        pos,
+                        exp,
+                        blk,
+                        alt: Vec::new(),
+                        els: Some(els),
+                    },
+                    None => SyntaxTree::SeStmt {
+                        pos,
+                        exp: Expression::Literal(Literal::Booleano(true), None),
+                        blk: els,
+                        alt: Vec::new(),
+                        els: None,
+                    },
+                };
+                out.push_stmt(stmt);
+            } else {
+                for stmt in els.take_stmts() {
+                    out.push_stmt(stmt);
+                }
+            }
+        }
+        return Ok(());
+    };
+
+    out.push_stmt(SyntaxTree::SeStmt {
+        pos,
+        exp,
+        blk,
+        alt: branches.collect(),
+        els,
+    });
+    Ok(())
+}
+
+/// whether `blk` declares variables, which end with it
+fn declares(blk: &Block) -> bool {
+    blk.iter_stmts()
+        .any(|stmt| matches!(stmt, SyntaxTree::Assign { .. }))
+}
+
+fn is_bool(exp: &Expression, value: bool) -> bool {
+    matches!(exp, Expression::Literal(Literal::Booleano(b), _) if *b == value)
+}
+
+fn is_literal(exp: &Expression) -> bool {
+    matches!(exp, Expression::Literal(..))
+}
+
+/// replaces the operations and casts of literals in `exp` by their results,
+/// `pos` being the statement where `exp` is
+fn fold(exp: &mut Expression, pos: &TokenPos) -> Result<()> {
+    let constant = match exp {
+        Expression::Literal(..) | Expression::Identifier(..) => false,
+        Expression::BinOp { ope, lhs, rhs, .. } => {
+            fold(lhs, pos)?;
+            fold(rhs, pos)?;
+            !ope.is_atrib() && is_literal(lhs) && is_literal(rhs)
+        }
+        Expression::UnOp { exp, .. } | Expression::Cast(exp, _) => {
+            fold(exp, pos)?;
+            is_literal(exp)
+        }
+        Expression::Index { lst, idx, .. } => {
+            fold(lst, pos)?;
+            fold(idx, pos)?;
+            false
+        }
+        Expression::List { exp: items, .. }
+        | Expression::Function { arg: items, .. }
+        | Expression::Interpolation(items) => {
+            for item in items {
+                fold(item, pos)?;
+            }
+            false
+        }
+    };
+
+    if constant {
+        *exp = Expression::Literal(evaluate(exp, pos)?, None);
+    }
+    Ok(())
+}
+
+/// runs `exp` on the vm, so the folded value is the one the program would get
+fn evaluate<'a>(exp: &Expression<'a>, pos: &TokenPos) -> Result<Literal<'a>> {
+    let error = |msg: String| CompileError {
+        pos: pos.clone(),
+        msg,
+    };
+
+    let code = compile_constant(exp).map_err(|err| error(err.msg))?;
+    let mut vm = LinaVm::new(code, empty(), sink()).expect("code of literals should be valid");
+    vm.run().map_err(|err| error(err.err.to_string()))?;
+
+    let literal = match vm.stack().last() {
+        Some(LinaValue::Int64(value)) => Literal::Inteiro(*value),
+        Some(LinaValue::Float64(value)) => Literal::Decimal(*value),
+        Some(LinaValue::String(value)) => Literal::Texto(Cow::Owned(value.clone())),
+        Some(LinaValue::Boolean(value)) => Literal::Booleano(*value),
+        value => unreachable!("operations of literals give a literal, not {value:?}"),
+    };
+    Ok(literal)
+}
@@ -2,13 +2,31 @@ use std::collections::HashMap;
 use std::iter::Peekable;
 use std::vec::IntoIter;
 
-use crate::syntax::{Block, Expression, Program, SyntaxTree, Type};
-use crate::token::{Delimitador, Literal, OpAssoc, OpInfo, Operador, Token, TokenDef, TokenPos};
+use crate::diagnostic::Label;
+use crate::syntax::{Block, Declaration, Expression, Program, SyntaxTree, Type};
+use crate::token::{
+    Delimitador, Literal, OpAssoc, OpInfo, Operador, Parte, Token, TokenDef, TokenPos,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct SyntaxError {
     pub pos: TokenPos,
     pub msg: String,
+    pub end: Option<TokenPos>, // end of the token at pos, filled in by the parser
+    pub labels: Vec<Label>,
+    pub hint: Option<String>,
+}
+
+impl SyntaxError {
+    fn label(mut self, pos: TokenPos, msg: impl Into<String>) -> Self {
+        self.labels.push(Label::new(pos, msg));
+        self
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
 }
 
 impl std::fmt::Display for SyntaxError {
@@ -20,41 +38,175 @@ impl std::fmt::Display for SyntaxError {
 
 type Result<T> = std::result::Result<T, SyntaxError>;
 
+/// every syntax error found in a program, with the part of it that could be parsed
+#[derive(Debug)]
+pub struct SyntaxErrors<'a> {
+    pub errors: Vec<SyntaxError>,
+    pub program: Program<'a>,
+}
+
+#[derive(Clone)]
 struct Symbol {
     pos: TokenPos,
     typ: Type,
 }
 
+#[derive(Clone)]
+struct Signature {
+    par: Option<Vec<Type>>, // None for native functions, which accept any arguments
+    ret: Type,
+}
+
 type TokenTable<'a> = HashMap<&'a str, Symbol>;
+type FunctionTable<'a> = HashMap<&'a str, Signature>;
 
 pub struct Parser<'a> {
     tokens: Peekable<IntoIter<TokenDef<'a>>>,
     symbols: Vec<TokenTable<'a>>,
+    hidden: Vec<TokenTable<'a>>, // scopes outside the function being parsed, which it does not see
+    functions: FunctionTable<'a>,
+    ret: Option<Type>,      // return type of the function being parsed
+    loops: usize,           // how many loops enclose the current statement
+    last: TokenPos,         // position of the last consumed token
+    bodies: usize, // how many blocks were started, to know if an error came before the body
+    params: TokenTable<'a>, // parameters read before an error in the header of a function
+    errors: Vec<SyntaxError>,
+    ends: HashMap<(usize, usize), TokenPos>, // where each consumed token ends, by its start
+}
+
+fn coerce<'a>(exp: Expression<'a>, typ: &Type) -> std::result::Result<Expression<'a>, String> {
+    // list literals are converted item by item, so [] and [1, 2] fit any list
+    let exp = match (typ, exp) {
+        (Type::List(elm), Expression::List { exp, .. }) => {
+            let exp = exp
+                .into_iter()
+                .map(|item| coerce(item, elm))
+                .collect::<std::result::Result<_, _>>()?;
+            return Ok(Expression::List {
+                exp,
+                typ: typ.clone(),
+            });
+        }
+        (_, exp) => exp,
+    };
+
+    let exp_typ = exp.get_type();
+
+    match (typ, &exp_typ) {
+        (x, y) if x == y => Ok(exp),
+        (Type::Integer, Type::Real) => Ok(Expression::Cast(Box::new(exp), Type::Integer)),
+        (Type::Real, Type::Integer) => Ok(Expression::Cast(Box::new(exp), Type::Real)),
+        (Type::Text, Type::Integer | Type::Real | Type::Boolean) => {
+            Ok(Expression::Cast(Box::new(exp), Type::Text))
+        }
+        _ => Err(format!("tipos incompatíveis {typ} e {exp_typ}")),
+    }
+}
+
+fn default_value<'a>(typ: &Type) -> Expression<'a> {
+    match typ {
+        Type::Integer => Expression::Literal(Literal::Inteiro(0), None),
+        Type::Real => Expression::Literal(Literal::Decimal(0.0), None),
+        Type::Text => Expression::Literal(Literal::Texto("".into()), None),
+        Type::Boolean => Expression::Literal(Literal::Booleano(false), None),
+        Type::List(..) => Expression::List {
+            exp: Vec::new(),
+            typ: typ.clone(),
+        },
+        Type::Void => unreachable!(),
+    }
+}
+
+fn check_native<'a>(
+    idt: &str,
+    mut arg: Vec<Expression<'a>>,
+    ret: &Type,
+) -> std::result::Result<(Vec<Expression<'a>>, Type), String> {
+    let expected = match idt {
+        "tamanho" => 1,
+        "adicionar" | "remover" => 2,
+        _ => return Ok((arg, ret.clone())), // saida and entrada take any arguments
+    };
+
+    if arg.len() != expected {
+        Err(format!(
+            "função {idt} espera {expected} argumento(s), encontrou {}",
+            arg.len()
+        ))?
+    }
+
+    match (idt, arg[0].get_type()) {
+        ("tamanho", Type::List(..) | Type::Text) => Ok((arg, Type::Integer)),
+        ("tamanho", typ) => Err(format!(
+            "tamanho espera uma lista ou texto, encontrou {typ}"
+        )),
+        ("adicionar", Type::List(elm)) => {
+            let val = arg.pop().unwrap();
+            arg.push(coerce(val, &elm)?);
+            Ok((arg, Type::Void))
+        }
+        ("remover", Type::List(elm)) => match arg[1].get_type() {
+            Type::Integer => Ok((arg, *elm)),
+            typ => Err(format!(
+                "o índice deve ser do tipo inteiro, encontrou {typ}"
+            )),
+        },
+        (_, typ) => Err(format!("{idt} espera uma lista, encontrou {typ}")),
+    }
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: Vec<TokenDef<'a>>) -> Self {
-        let defaults = [
+        let natives = [
             (
                 "saida",
-                Symbol {
-                    pos: TokenPos::default(),
-                    typ: Type::Void,
+                Signature {
+                    par: None,
+                    ret: Type::Void,
                 },
             ),
             (
                 "entrada",
-                Symbol {
-                    pos: TokenPos::default(),
-                    typ: Type::Void, // retornar boolean
+                Signature {
+                    par: None,
+                    ret: Type::Void, // retornar boolean
+                },
+            ),
+            (
+                "tamanho",
+                Signature {
+                    par: None,
+                    ret: Type::Integer,
+                },
+            ),
+            (
+                "adicionar",
+                Signature {
+                    par: None,
+                    ret: Type::Void,
+                },
+            ),
+            (
+                "remover",
+                Signature {
+                    par: None,
+                    ret: Type::Void, // tipo do item da lista
                 },
             ),
         ];
-        let globals = TokenTable::from(defaults);
 
         Parser {
             tokens: tokens.into_iter().peekable(),
-            symbols: vec![globals],
+            symbols: vec![TokenTable::new()],
+            hidden: Vec::new(),
+            functions: FunctionTable::from(natives),
+            ret: None,
+            loops: 0,
+            last: TokenPos::default(),
+            bodies: 0,
+            params: TokenTable::new(),
+            errors: Vec::new(),
+            ends: HashMap::new(),
         }
     }
 
@@ -62,13 +214,40 @@ impl<'a> Parser<'a> {
         self.tokens.peek()
     }
 
+    fn next_token(&mut self) -> Option<TokenDef<'a>> {
+        let def = self.tokens.next()?;
+        self.last = def.pos.clone();
+        self.ends
+            .insert((def.pos.row, def.pos.col), def.end.clone());
+        Some(def)
+    }
+
     fn advance(&mut self) -> Result<TokenDef<'a>> {
-        self.tokens.next().ok_or_else(|| SyntaxError {
-            pos: TokenPos::default(),
+        self.next_token().ok_or_else(|| SyntaxError {
+            pos: self.last.clone(),
             msg: "fim inesperado do arquivo".into(),
+            ..Default::default()
         })
     }
 
+    fn end_of(&mut self, pos: &TokenPos) -> Option<TokenPos> {
+        if let Some(end) = self.ends.get(&(pos.row, pos.col)) {
+            return Some(end.clone());
+        }
+        self.peek()
+            .filter(|la| la.pos == *pos)
+            .map(|la| la.end.clone())
+    }
+
+    /// keeps `err`, spanning the whole tokens it points at
+    fn report(&mut self, mut err: SyntaxError) {
+        err.end = self.end_of(&err.pos);
+        for label in &mut err.labels {
+            label.end = self.end_of(&label.pos);
+        }
+        self.errors.push(err);
+    }
+
     fn set_symbol(&mut self, name: &'a str, pos: TokenPos, typ: Type) {
         let scope = self.symbols.last_mut().unwrap();
         scope.insert(name, Symbol { pos, typ });
@@ -92,21 +271,23 @@ impl<'a> Parser<'a> {
     }
 
     fn consume_invariant(&mut self, expected: Token) -> Result<()> {
-        match self.tokens.next() {
+        match self.next_token() {
             Some(def) if def.tok == expected => Ok(()),
             Some(def) => Err(SyntaxError {
                 pos: def.pos,
                 msg: format!("esperado {}, encontrou {}", expected, def.tok),
+                ..Default::default()
             }),
             None => Err(SyntaxError {
-                pos: TokenPos::default(),
+                pos: self.last.clone(),
                 msg: format!("esperado {}, encontrou o final do arquivo", expected),
+                ..Default::default()
             }),
         }
     }
 
     fn consume_identifier(&mut self) -> Result<&'a str> {
-        match self.tokens.next() {
+        match self.next_token() {
             Some(TokenDef {
                 tok: Token::Identificador(ident),
                 ..
@@ -114,33 +295,39 @@ impl<'a> Parser<'a> {
             Some(def) => Err(SyntaxError {
                 pos: def.pos,
                 msg: format!("esperado identificador, encontrou {}", def.tok),
+                ..Default::default()
             }),
             None => Err(SyntaxError {
-                pos: TokenPos::default(),
-                msg: format!("esperado identificador, encontrou o final do arquivo"),
+                pos: self.last.clone(),
+                msg: "esperado identificador, encontrou o final do arquivo".into(),
+                ..Default::default()
             }),
         }
     }
 
-    fn consume_literal(&mut self) -> Result<Literal<'a>> {
-        match self.tokens.next() {
+    /// a literal and how it was written
+    fn consume_literal(&mut self) -> Result<(Literal<'a>, &'a str)> {
+        match self.next_token() {
             Some(TokenDef {
                 tok: Token::Literal(literal),
+                src,
                 ..
-            }) => Ok(literal),
+            }) => Ok((literal, src)),
             Some(def) => Err(SyntaxError {
                 pos: def.pos,
                 msg: format!("esperado literal, encontrou {}", def.tok),
+                ..Default::default()
             }),
             None => Err(SyntaxError {
-                pos: TokenPos::default(),
-                msg: format!("esperado literal, encontrou o final do arquivo"),
+                pos: self.last.clone(),
+                msg: "esperado literal, encontrou o final do arquivo".into(),
+                ..Default::default()
             }),
         }
     }
 
     fn consume_operator(&mut self) -> Result<Operador> {
-        match self.tokens.next() {
+        match self.next_token() {
             Some(TokenDef {
                 tok: Token::Operador(operador),
                 ..
@@ -148,99 +335,300 @@ impl<'a> Parser<'a> {
             Some(def) => Err(SyntaxError {
                 pos: def.pos,
                 msg: format!("esperado operador, encontrou {}", def.tok),
+                ..Default::default()
             }),
             None => Err(SyntaxError {
-                pos: TokenPos::default(),
-                msg: format!("esperado operador, encontrou o final do arquivo"),
+                pos: self.last.clone(),
+                msg: "esperado operador, encontrou o final do arquivo".into(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<Type> {
+        let TokenDef { tok, pos, .. } = self.advance()?;
+        match tok {
+            Token::Inteiro => Ok(Type::Integer),
+            Token::Real => Ok(Type::Real),
+            Token::Texto => Ok(Type::Text),
+            Token::Booleano => Ok(Type::Boolean),
+            Token::Lista => {
+                self.consume_invariant(Token::De)?;
+                Ok(Type::List(Box::new(self.parse_type()?)))
+            }
+            _ => Err(SyntaxError {
+                pos,
+                msg: format!("esperado tipo, encontrou {tok}"),
+                ..Default::default()
             }),
         }
     }
 
+    fn parse_params(&mut self) -> Result<Vec<(&'a str, Type)>> {
+        self.consume_invariant(Token::Delimitador(Delimitador::AParen))?;
+
+        let mut par = Vec::new();
+        if let Some(TokenDef {
+            tok: Token::Delimitador(Delimitador::FParen),
+            ..
+        }) = self.peek()
+        {
+            self.advance()?;
+            return Ok(par);
+        }
+
+        loop {
+            let pos = self.peek().map(|la| la.pos.clone()).unwrap_or_default();
+            let typ = self.parse_type()?;
+            let idt = self.consume_identifier()?;
+
+            if let Some(Symbol { pos: prev, .. }) = self.get_symbol(idt) {
+                let prev = prev.clone();
+                Err(SyntaxError {
+                    msg: format!("parâmetro {idt} declarado mais de uma vez"),
+                    pos: pos.clone(),
+                    ..Default::default()
+                }
+                .label(prev, "declarado pela primeira vez aqui"))?
+            }
+
+            self.set_symbol(idt, pos, typ.clone());
+            par.push((idt, typ));
+
+            let TokenDef { tok, pos, .. } = self.advance()?;
+            match tok {
+                Token::Delimitador(Delimitador::Virgula) => continue,
+                Token::Delimitador(Delimitador::FParen) => break,
+                _ => Err(SyntaxError {
+                    msg: format!("esperado vírgula ou parênteses de fechamento, encontrou {tok}"),
+                    pos,
+                    ..Default::default()
+                })?,
+            }
+        }
+
+        Ok(par)
+    }
+
+    fn parse_function(&mut self, idt: &'a str, pos: TokenPos) -> Result<SyntaxTree<'a>> {
+        let par = self.parse_params()?;
+
+        let ret = match self.peek() {
+            Some(TokenDef {
+                tok: Token::Delimitador(Delimitador::DoisPontos),
+                ..
+            }) => {
+                self.advance()?;
+                self.parse_type()?
+            }
+            _ => Type::Void,
+        };
+
+        // registered before the body so the function can call itself
+        let signature = Signature {
+            par: Some(par.iter().map(|(_, typ)| typ.clone()).collect()),
+            ret: ret.clone(),
+        };
+        self.functions.insert(idt, signature);
+
+        self.ret = Some(ret.clone());
+        let errors = self.errors.len();
+        let blk = self.parse_block()?;
+
+        // a body with errors may have lost the retorne that ends it
+        if ret != Type::Void && !blk.returns() && self.errors.len() == errors {
+            Err(SyntaxError {
+                msg: format!("a função {idt} deve retornar um valor do tipo {ret}"),
+                pos: pos.clone(),
+                ..Default::default()
+            }
+            .hint("todos os caminhos da função devem terminar com `retorne`"))?
+        }
+
+        Ok(SyntaxTree::FuncaoStmt {
+            pos,
+            idt,
+            par,
+            ret,
+            blk,
+        })
+    }
+
+    fn parse_declaration(
+        &mut self,
+        pos: TokenPos,
+        decl: Option<Type>,
+        idt: &'a str,
+    ) -> Result<SyntaxTree<'a>> {
+        if let Some(Symbol { pos: prev, .. }) = self.get_symbol(idt) {
+            let prev = prev.clone();
+            Err(SyntaxError {
+                msg: format!("redeclaração da variável {idt}"),
+                pos: pos.clone(),
+                ..Default::default()
+            }
+            .label(prev, "variável declarada aqui")
+            .hint(format!(
+                "para mudar o valor de {idt}, use `{idt} := ...` sem declarar de novo"
+            )))?
+        }
+
+        if let Some(TokenDef {
+            tok: Token::Operador(Operador::Atrib),
+            ..
+        }) = self.peek()
+        {
+            self.advance()?;
+            let exp_pos = self.peek().map(|v| v.pos.clone());
+            let exp = self.parse_expression(1)?;
+            let seja = decl.is_none();
+            let typ = decl.unwrap_or_else(|| exp.get_type());
+
+            if !typ.is_complete() {
+                Err(SyntaxError {
+                    msg: format!("não foi possível determinar o tipo de {idt}"),
+                    pos: exp_pos.clone().unwrap(),
+                    ..Default::default()
+                }
+                .hint(format!(
+                    "declare o tipo da variável, como em `lista de inteiro {idt} := []`"
+                )))?
+            }
+
+            let exp = match exp {
+                Expression::List { .. } => coerce(exp, &typ).map_err(|msg| SyntaxError {
+                    pos: exp_pos.clone().unwrap(),
+                    msg,
+                    ..Default::default()
+                })?,
+                exp => exp,
+            };
+
+            let exp_typ = exp.get_type();
+            if typ != exp_typ {
+                Err(SyntaxError {
+                    msg: format!("{exp_typ} não pode ser convertido para {typ}"),
+                    pos: exp_pos.unwrap(),
+                    ..Default::default()
+                })?
+            }
+
+            self.set_symbol(idt, pos.clone(), typ.clone());
+
+            let dcl = if seja {
+                Declaration::Seja
+            } else {
+                Declaration::Typed
+            };
+            Ok(SyntaxTree::Assign {
+                pos,
+                dcl,
+                idt,
+                exp,
+                typ,
+            })
+        } else {
+            let Some(typ) = decl else {
+                return Err(SyntaxError {
+                    msg: "seja não pode ser usado sem inicializador".into(),
+                    pos,
+                    ..Default::default()
+                }
+                .hint(format!(
+                    "dê um valor inicial, como em `seja {idt} := 0`, ou declare o tipo, como em `inteiro {idt}`"
+                )));
+            };
+            let exp = default_value(&typ);
+
+            self.set_symbol(idt, pos.clone(), typ.clone());
+
+            Ok(SyntaxTree::Assign {
+                pos,
+                dcl: Declaration::Default,
+                idt,
+                exp,
+                typ,
+            })
+        }
+    }
+
     fn parse_statement(&mut self) -> Result<SyntaxTree<'a>> {
         let token_ref = self.peek().unwrap();
         let pos = token_ref.pos.clone();
 
         let stmt = match token_ref.tok {
-            Token::Seja | Token::Inteiro | Token::Real | Token::Booleano | Token::Texto => {
-                let decl = self.advance()?;
-                let idt = self.consume_identifier()?;
-
-                if self.get_symbol(idt).is_some() {
-                    Err(SyntaxError {
-                        msg: format!("redeclaração da variável {idt}"),
-                        pos: decl.pos.clone(),
-                    })?
-                }
-
-                if let Some(TokenDef {
-                    tok: Token::Operador(Operador::Atrib),
-                    pos: _,
+            Token::Seja
+            | Token::Inteiro
+            | Token::Real
+            | Token::Booleano
+            | Token::Texto
+            | Token::Lista => {
+                // seja takes the type of the initializer
+                let decl = if let Some(TokenDef {
+                    tok: Token::Seja, ..
                 }) = self.peek()
                 {
                     self.advance()?;
-                    let exp_pos = self.peek().and_then(|v| Some(v.pos.clone()));
-                    let exp = self.parse_expression(1)?;
-
-                    let typ = match decl.tok {
-                        Token::Seja => exp.get_type(),
-                        Token::Inteiro => Type::Integer,
-                        Token::Real => Type::Real,
-                        Token::Texto => Type::Text,
-                        Token::Booleano => Type::Boolean,
-                        _ => unreachable!(),
-                    };
-
-                    let exp_typ = exp.get_type();
-                    if typ != exp_typ {
-                        Err(SyntaxError {
-                            msg: format!("{exp_typ} não pode ser convertido para {typ}"),
-                            pos: exp_pos.unwrap(),
-                        })?
-                    }
-
-                    self.set_symbol(idt, pos.clone(), typ.clone());
-
-                    SyntaxTree::Assign { pos, idt, exp, typ }
+                    None
                 } else {
-                    let (typ, ini) = match decl.tok {
-                        Token::Seja => Err(SyntaxError {
-                            msg: format!("seja não pode ser usado sem inicializador"),
-                            pos: decl.pos,
-                        })?,
-                        Token::Inteiro => (Type::Integer, Literal::Inteiro(0)),
-                        Token::Real => (Type::Real, Literal::Decimal(0.0)),
-                        Token::Texto => (Type::Text, Literal::Texto("")),
-                        Token::Booleano => (Type::Boolean, Literal::Booleano(false)),
-                        _ => unreachable!(),
-                    };
-                    let exp = Expression::Literal(ini);
+                    Some(self.parse_type()?)
+                };
+
+                let idt = self.consume_identifier()?;
 
-                    self.set_symbol(idt, pos.clone(), typ.clone());
+                let typ = decl.clone();
+                let stmt = self.parse_declaration(pos.clone(), decl, idt);
 
-                    SyntaxTree::Assign { pos, idt, exp, typ }
+                // a typed variable is known even when its declaration has errors,
+                // so its uses are not reported as undefined too
+                if let (Err(_), Some(typ)) = (&stmt, typ) {
+                    if self.get_symbol(idt).is_none() {
+                        self.set_symbol(idt, pos, typ);
+                    }
                 }
+                stmt?
             }
             Token::Enquanto => {
                 self.consume_invariant(Token::Enquanto)?;
-                let exp = self.parse_expression(1)?;
-                if exp.get_type() != Type::Boolean {
-                    let msg = format!("esperado expressão do tipo booleano");
-                    Err(SyntaxError { msg, pos })?;
-                }
+                let exp = self.parse_condition()?;
                 self.consume_invariant(Token::Repetir)?;
-                let blk = self.parse_block()?;
-                SyntaxTree::EnquantoStmt { exp, blk }
+                let blk = self.parse_loop()?;
+                SyntaxTree::EnquantoStmt { pos, exp, blk }
             }
             Token::Se => {
                 self.consume_invariant(Token::Se)?;
-                let exp = self.parse_expression(1)?;
-                if exp.get_type() != Type::Boolean {
-                    let msg = format!("esperado expressão do tipo booleano");
-                    Err(SyntaxError { msg, pos })?;
-                }
+                let exp = self.parse_condition()?;
                 self.consume_invariant(Token::Entao)?;
-                let blk = self.parse_block()?;
-                SyntaxTree::SeStmt { exp, blk }
+                let blk = self.parse_body()?;
+
+                let mut alt = Vec::new();
+                let mut els = None;
+
+                while let Some(TokenDef {
+                    tok: Token::Senao, ..
+                }) = self.peek()
+                {
+                    self.advance()?;
+
+                    if let Some(TokenDef { tok: Token::Se, .. }) = self.peek() {
+                        self.advance()?;
+                        let exp = self.parse_condition()?;
+                        self.consume_invariant(Token::Entao)?;
+                        alt.push((exp, self.parse_body()?));
+                    } else {
+                        els = Some(self.parse_body()?);
+                        break;
+                    }
+                }
+
+                self.consume_invariant(Token::Fim)?;
+                SyntaxTree::SeStmt {
+                    pos,
+                    exp,
+                    blk,
+                    alt,
+                    els,
+                }
             }
             Token::Para => {
                 self.consume_invariant(Token::Para)?;
@@ -250,24 +638,28 @@ impl<'a> Parser<'a> {
 
                 if let Some(Symbol { pos, typ }) = self.find_symbol(idt) {
                     if *typ != Type::Integer {
+                        let prev = pos.clone();
                         Err(SyntaxError {
                             msg: format!("variavel {idt} não é do tipo {idt_typ}"),
-                            pos: pos.clone(),
-                        })?
+                            pos: idt_pos.clone(),
+                            ..Default::default()
+                        }
+                        .label(prev, "variável declarada aqui"))?
                     }
                 } else {
-                    self.set_symbol(idt, idt_pos, idt_typ.clone());
+                    self.set_symbol(idt, idt_pos.clone(), idt_typ.clone());
                 }
 
-                let TokenDef { tok, pos } = self.advance()?;
+                let TokenDef { tok, pos, .. } = self.advance()?;
                 let sta = match tok {
                     Token::Operador(Operador::Atrib) => {
                         let sta = self.consume_literal()?;
-                        let sta_typ = Type::from(&sta);
+                        let sta_typ = Type::from(&sta.0);
                         if sta_typ != Type::Integer {
                             return Err(SyntaxError {
                                 msg: format!("o tipo {idt_typ} não é permitido para o laço para"),
                                 pos,
+                                ..Default::default()
                             });
                         }
                         self.consume_invariant(Token::Ate)?;
@@ -278,11 +670,12 @@ impl<'a> Parser<'a> {
                         return Err(SyntaxError {
                             msg: format!("esperado atribuição ou ate, encontrou {tok}"),
                             pos,
+                            ..Default::default()
                         })
                     }
                 };
 
-                let lmt_pos = self.peek().and_then(|la| Some(la.pos.clone()));
+                let lmt_pos = self.peek().map(|la| la.pos.clone());
                 let lmt = self.parse_expression(1)?;
 
                 match lmt.get_type() {
@@ -290,20 +683,22 @@ impl<'a> Parser<'a> {
                     disallow => Err(SyntaxError {
                         msg: format!("o tipo {disallow} não é permitido como limite"),
                         pos: lmt_pos.unwrap(),
+                        ..Default::default()
                     }),
                 }?;
 
-                let TokenDef { tok, pos } = self.advance()?;
+                let TokenDef { tok, pos, .. } = self.advance()?;
 
                 let stp = match tok {
                     Token::Incremento => {
-                        let stp_pos = self.peek().and_then(|la| Some(la.pos.clone()));
+                        let stp_pos = self.peek().map(|la| la.pos.clone());
                         let stp = self.consume_literal()?;
-                        let stp_typ = Type::from(&stp);
+                        let stp_typ = Type::from(&stp.0);
                         if stp_typ != Type::Integer {
                             return Err(SyntaxError {
                                 msg: format!("tipo do incremento é incompátivel com a variável. variável: {idt_typ}, incremento: {stp_typ}"),
                                 pos: stp_pos.unwrap(),
+                                ..Default::default()
                             });
                         }
                         self.consume_invariant(Token::Repetir)?;
@@ -314,12 +709,14 @@ impl<'a> Parser<'a> {
                         return Err(SyntaxError {
                             msg: format!("esperado incremento ou repetir, encontrou {tok}"),
                             pos,
+                            ..Default::default()
                         });
                     }
                 };
 
-                let blk = self.parse_block()?;
+                let blk = self.parse_loop()?;
                 SyntaxTree::ParaStmt {
+                    pos: idt_pos,
                     idt,
                     sta,
                     lmt,
@@ -327,16 +724,101 @@ impl<'a> Parser<'a> {
                     blk,
                 }
             }
-            Token::Funcao => todo!(),
-            Token::Retorne => todo!(),
-            Token::Identificador(..) | Token::Literal(..) | Token::Delimitador(..) => {
+            Token::Funcao => {
+                self.consume_invariant(Token::Funcao)?;
+
+                if self.ret.is_some() || self.symbols.len() > 1 {
+                    Err(SyntaxError {
+                        msg: "funções só podem ser declaradas no escopo do programa".into(),
+                        pos: pos.clone(),
+                        ..Default::default()
+                    })?
+                }
+
+                let idt = self.consume_identifier()?;
+                if self.functions.contains_key(idt) {
+                    Err(SyntaxError {
+                        msg: format!("redeclaração da função {idt}"),
+                        pos: pos.clone(),
+                        ..Default::default()
+                    })?
+                }
+
+                // each function has its own symbol table, starting with its parameters
+                let outer = std::mem::replace(&mut self.symbols, vec![TokenTable::new()]);
+                let hidden = std::mem::replace(&mut self.hidden, outer);
+                let loops = std::mem::take(&mut self.loops);
+                let function = self.parse_function(idt, pos);
+                let outer = std::mem::replace(&mut self.hidden, hidden);
+                let inner = std::mem::replace(&mut self.symbols, outer);
+                if function.is_err() {
+                    self.params = inner.into_iter().next().unwrap_or_default();
+                }
+                self.loops = loops;
+                self.ret = None;
+
+                function?
+            }
+            Token::Retorne => {
+                self.consume_invariant(Token::Retorne)?;
+
+                let Some(ret) = self.ret.clone() else {
+                    return Err(SyntaxError {
+                        msg: "retorne só pode ser usado dentro de uma função".into(),
+                        pos,
+                        ..Default::default()
+                    });
+                };
+
+                let exp = match ret {
+                    Type::Void => None,
+                    typ => {
+                        let exp_pos = self.peek().map(|la| la.pos.clone()).unwrap_or_default();
+                        let exp = self.parse_expression(1)?;
+                        let exp = coerce(exp, &typ).map_err(|msg| SyntaxError {
+                            pos: exp_pos,
+                            msg,
+                            ..Default::default()
+                        })?;
+                        Some(exp)
+                    }
+                };
+
+                SyntaxTree::RetorneStmt { pos, exp }
+            }
+            Token::Pare | Token::Continue => {
+                let TokenDef { tok, pos, .. } = self.advance()?;
+
+                if self.loops == 0 {
+                    return Err(SyntaxError {
+                        msg: format!("{tok} só pode ser usado dentro de um laço"),
+                        pos,
+                        ..Default::default()
+                    }
+                    .hint("os laços são `enquanto ... repetir` e `para ... repetir`"));
+                }
+
+                match tok {
+                    Token::Pare => SyntaxTree::PareStmt { pos },
+                    _ => SyntaxTree::ContinueStmt { pos },
+                }
+            }
+            Token::Identificador(..)
+            | Token::Literal(..)
+            | Token::Interpolacao(..)
+            | Token::Delimitador(..)
+            | Token::Operador(Operador::Subt | Operador::Nao) => {
                 let expression = self.parse_expression(1)?;
-                SyntaxTree::Expr(expression)
+                SyntaxTree::Expr {
+                    pos,
+                    exp: expression,
+                }
             }
             _ => {
                 return Err(SyntaxError {
                     msg: format!("token inesperado {}", token_ref.tok),
                     pos,
+                    ..Default::default()
                 });
             }
         };
@@ -350,28 +832,9 @@ impl<'a> Parser<'a> {
         rhs: Expression<'a>,
         ope: Operador,
     ) -> std::result::Result<Expression<'a>, String> {
-        if let Expression::Identifier(..) = &lhs {
-            let lhs_typ = lhs.get_type();
-            let rhs_typ = rhs.get_type();
-
-            let mut right = rhs;
-
-            let typ = match (&lhs_typ, &rhs_typ) {
-                (x, y) if x == y => x.clone(),
-                (Type::Integer, Type::Real) => {
-                    right = Expression::Cast(Box::new(right), Type::Integer);
-                    Type::Integer
-                }
-                (Type::Real, Type::Integer) => {
-                    right = Expression::Cast(Box::new(right), Type::Real);
-                    Type::Real
-                }
-                (Type::Text, Type::Integer | Type::Real | Type::Boolean) => {
-                    right = Expression::Cast(Box::new(right), Type::Text);
-                    Type::Text
-                }
-                _ => Err(format!("tipos incompatíveis {lhs_typ} e {rhs_typ}"))?,
-            };
+        if let Expression::Identifier(..) | Expression::Index { .. } = &lhs {
+            let typ = lhs.get_type();
+            let right = coerce(rhs, &typ)?;
 
             Ok(Expression::BinOp {
                 typ,
@@ -380,7 +843,7 @@ impl<'a> Parser<'a> {
                 rhs: Box::new(right),
             })
         } else {
-            Err(format!("lado esquerdo deve ser um identificador"))
+            Err("lado esquerdo deve ser um identificador ou um item de lista".into())
         }
     }
 
@@ -390,15 +853,22 @@ impl<'a> Parser<'a> {
         rhs: Expression<'a>,
         ope: Operador,
     ) -> std::result::Result<Expression<'a>, String> {
-        let lhs_typ = lhs.get_type();
-        let rhs_typ = rhs.get_type();
+        let mut left = lhs;
+        let mut right = rhs;
+
+        // a list literal takes the type of the list on the other side, as in xs = []
+        if let (Type::List(..), Expression::List { .. }) = (left.get_type(), &right) {
+            right = coerce(right, &left.get_type())?;
+        } else if let (Expression::List { .. }, Type::List(..)) = (&left, right.get_type()) {
+            left = coerce(left, &right.get_type())?;
+        }
+
+        let lhs_typ = left.get_type();
+        let rhs_typ = right.get_type();
 
         use Operador::*;
         use Type::*;
 
-        let mut left = lhs;
-        let mut right = rhs;
-
         let result_typ = match (&lhs_typ, &rhs_typ) {
             // default case
             (x, y) if x == y => x.clone(),
@@ -446,9 +916,29 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn parse_args(&mut self) -> Result<Vec<Expression<'a>>> {
-        let open_paren = self.advance()?;
+    fn parse_unop(
+        &mut self,
+        exp: Expression<'a>,
+        ope: Operador,
+    ) -> std::result::Result<Expression<'a>, String> {
+        use Operador::*;
+        use Type::*;
+
+        let typ = match (&ope, exp.get_type()) {
+            (Subt, r @ (Integer | Real)) => r,
+            (Nao, Boolean) => Boolean,
+            (_, typ) => Err(format!("operação {ope} não suportada para {typ}"))?,
+        };
+
+        Ok(Expression::UnOp {
+            typ,
+            ope,
+            exp: Box::new(exp),
+        })
+    }
 
+    /// parses a comma separated list of expressions, after the opening delimiter at `open`
+    fn parse_args(&mut self, open: TokenPos, close: Delimitador) -> Result<Vec<Expression<'a>>> {
         enum States {
             S1,
             S2,
@@ -460,8 +950,8 @@ impl<'a> Parser<'a> {
         let mut arg = Vec::new();
         while let Some(lookahead) = self.peek() {
             match (&state, &lookahead.tok) {
-                (States::S1 | States::S2, Token::Delimitador(Delimitador::FParen)) => {
-                    _ = self.advance(); // discard parenthesis
+                (States::S1 | States::S2, Token::Delimitador(del)) if *del == close => {
+                    _ = self.advance(); // discard closing delimiter
                     state = States::S4;
                     break;
                 }
@@ -475,7 +965,8 @@ impl<'a> Parser<'a> {
                 }
                 (States::S2, ..) => Err(SyntaxError {
                     pos: lookahead.pos.clone(),
-                    msg: "experado parênteses de fechamento".into(),
+                    msg: format!("experado {close} de fechamento"),
+                    ..Default::default()
                 })?,
                 (States::S4, ..) => unreachable!(),
             }
@@ -484,63 +975,269 @@ impl<'a> Parser<'a> {
         match state {
             States::S4 => Ok(arg),
             _ => Err(SyntaxError {
-                pos: open_paren.pos,
-                msg: "experado parênteses de fechamento".into(),
+                pos: open,
+                msg: format!("experado {close} de fechamento"),
+                ..Default::default()
             }),
         }
     }
 
     fn parse_atom(&mut self) -> Result<Expression<'a>> {
-        let TokenDef { tok, pos } = self.advance()?;
-        let lookahead = self.peek().and_then(|x| Some(&x.tok));
+        // keywords are not consumed, they may start the next statement
+        if let Some(TokenDef { tok, pos, .. }) = self.peek() {
+            if !matches!(
+                tok,
+                Token::Identificador(_)
+                    | Token::Literal(_)
+                    | Token::Interpolacao(_)
+                    | Token::Operador(_)
+                    | Token::Delimitador(_)
+            ) {
+                return Err(SyntaxError {
+                    pos: pos.clone(),
+                    msg: format!("token inesperado {tok}"),
+                    ..Default::default()
+                });
+            }
+        }
+
+        let TokenDef { tok, pos, src, .. } = self.advance()?;
+        let lookahead = self.peek().map(|x| &x.tok);
 
         let expression = match tok {
             Token::Identificador(idt)
                 if matches!(lookahead, Some(Token::Delimitador(Delimitador::AParen))) =>
             {
-                let arg = self.parse_args()?;
-
-                let symb = self.find_symbol(idt).ok_or_else(|| SyntaxError {
-                    pos,
-                    msg: format!("função não definida {idt}"),
+                let open = self.advance()?;
+                let arg = self.parse_args(open.pos, Delimitador::FParen)?;
+
+                let sign = self.functions.get(idt).ok_or_else(|| {
+                    SyntaxError {
+                        pos: pos.clone(),
+                        msg: format!("função não definida {idt}"),
+                        ..Default::default()
+                    }
+                    .hint(format!(
+                        "funções devem ser declaradas antes de usadas, com `função {idt}(...)`"
+                    ))
                 })?;
 
-                Expression::Function {
-                    idt,
-                    arg,
-                    ret: symb.typ.clone(),
-                }
+                let (arg, ret) = match &sign.par {
+                    None => check_native(idt, arg, &sign.ret).map_err(|msg| SyntaxError {
+                        pos: pos.clone(),
+                        msg,
+                        ..Default::default()
+                    })?,
+                    Some(par) if par.len() != arg.len() => Err(SyntaxError {
+                        pos,
+                        msg: format!(
+                            "função {idt} espera {} argumento(s), encontrou {}",
+                            par.len(),
+                            arg.len()
+                        ),
+                        ..Default::default()
+                    })?,
+                    Some(par) => {
+                        let arg = arg
+                            .into_iter()
+                            .zip(par)
+                            .map(|(exp, typ)| coerce(exp, typ))
+                            .collect::<std::result::Result<_, _>>()
+                            .map_err(|msg| SyntaxError {
+                                pos: pos.clone(),
+                                msg,
+                                ..Default::default()
+                            })?;
+                        (arg, sign.ret.clone())
+                    }
+                };
+
+                Expression::Function { idt, arg, ret }
             }
             Token::Identificador(idt) => {
-                let symb = self.find_symbol(idt).ok_or_else(|| SyntaxError {
-                    pos,
-                    msg: format!("variavel não definida {idt}"),
-                })?;
-
-                Expression::Identifier(idt, symb.typ.clone())
+                if let Some(symb) = self.find_symbol(idt) {
+                    Expression::Identifier(idt, symb.typ.clone())
+                } else {
+                    let err = SyntaxError {
+                        pos,
+                        msg: format!("variavel não definida {idt}"),
+                        ..Default::default()
+                    };
+                    let outside = self.hidden.iter().rev().find_map(|scope| scope.get(idt));
+                    Err(match outside {
+                        Some(Symbol { pos: decl, .. }) => err
+                            .label(decl.clone(), "declarada aqui, fora da função")
+                            .hint(format!(
+                                "funções só enxergam seus parâmetros e suas variáveis, passe {idt} como parâmetro"
+                            )),
+                        None => err.hint(format!(
+                            "declare a variável antes de usá-la, como em `seja {idt} := 0`"
+                        )),
+                    })?
+                }
             }
-            Token::Literal(literal) => Expression::Literal(literal),
+            Token::Literal(literal) => Expression::Literal(literal, Some(src)),
+            Token::Interpolacao(parts) => self.parse_interpolation(parts)?,
             Token::Delimitador(Delimitador::AParen) => {
                 let inner_expr = self.parse_expression(1)?;
                 self.consume_invariant(Token::Delimitador(Delimitador::FParen))?;
                 inner_expr
             }
+            Token::Delimitador(Delimitador::AColch) => self.parse_list(pos)?,
             _ => Err(SyntaxError {
                 pos,
                 msg: format!("token inesperado {tok}"),
+                ..Default::default()
             })?,
         };
-        Ok(expression)
+
+        self.parse_index(expression)
     }
 
-    fn parse_expression(&mut self, min_prec: u8) -> Result<Expression<'a>> {
-        let mut lhs = self.parse_atom()?;
+    /// parses the expressions embedded in a text, each one converted to texto
+    fn parse_interpolation(&mut self, parts: Vec<Parte<'a>>) -> Result<Expression<'a>> {
+        let mut exp = Vec::new();
 
-        loop {
-            let Some(def_ope) = self.peek() else {
-                break;
+        for part in parts {
+            let tokens = match part {
+                Parte::Texto(text, src) => {
+                    exp.push(Expression::Literal(Literal::Texto(text), Some(src)));
+                    continue;
+                }
+                Parte::Expressao(tokens) => tokens,
+            };
+
+            // the embedded tokens are parsed in place of the rest of the program
+            let pos = tokens[0].pos.clone();
+            let outer = std::mem::replace(&mut self.tokens, tokens.into_iter().peekable());
+            let inner = self.parse_expression(1).and_then(|inner| {
+                self.consume_invariant(Token::Delimitador(Delimitador::FChave))?;
+                Ok(inner)
+            });
+            self.tokens = outer;
+
+            let inner = inner?;
+            let typ = inner.get_type();
+            let inner = coerce(inner, &Type::Text).map_err(|_| {
+                SyntaxError {
+                    pos,
+                    msg: format!("o tipo {typ} não pode ser usado dentro de um texto"),
+                    ..Default::default()
+                }
+                .hint("apenas inteiro, real, booleano e texto podem ser escritos entre { e }")
+            })?;
+            exp.push(inner);
+        }
+
+        Ok(Expression::Interpolation(exp))
+    }
+
+    fn parse_list(&mut self, pos: TokenPos) -> Result<Expression<'a>> {
+        let exp = self.parse_args(pos.clone(), Delimitador::FColch)?;
+
+        // the items share a type, with inteiro promoted to real when they are mixed
+        let mut elm = Type::Void;
+        for item in exp.iter() {
+            elm = match (elm, item.get_type()) {
+                (Type::Void, y) => y,
+                (x, y) if x == y => x,
+                (Type::Integer, Type::Real) | (Type::Real, Type::Integer) => Type::Real,
+                (x, y) => Err(SyntaxError {
+                    pos: pos.clone(),
+                    msg: format!("itens da lista devem ser do mesmo tipo, encontrou {x} e {y}"),
+                    ..Default::default()
+                })?,
+            };
+        }
+
+        let typ = Type::List(Box::new(elm));
+        coerce(
+            Expression::List {
+                exp,
+                typ: typ.clone(),
+            },
+            &typ,
+        )
+        .map_err(|msg| SyntaxError {
+            pos,
+            msg,
+            ..Default::default()
+        })
+    }
+
+    fn parse_index(&mut self, mut lst: Expression<'a>) -> Result<Expression<'a>> {
+        while let Some(TokenDef {
+            tok: Token::Delimitador(Delimitador::AColch),
+            pos,
+            ..
+        }) = self.peek()
+        {
+            let pos = pos.clone();
+            self.advance()?;
+            let idx = self.parse_expression(1)?;
+            self.consume_invariant(Token::Delimitador(Delimitador::FColch))?;
+
+            let Type::List(typ) = lst.get_type() else {
+                return Err(SyntaxError {
+                    pos,
+                    msg: format!(
+                        "apenas listas podem ser indexadas, encontrou {}",
+                        lst.get_type()
+                    ),
+                    ..Default::default()
+                });
             };
 
+            if idx.get_type() != Type::Integer {
+                return Err(SyntaxError {
+                    pos,
+                    msg: format!(
+                        "o índice deve ser do tipo inteiro, encontrou {}",
+                        idx.get_type()
+                    ),
+                    ..Default::default()
+                });
+            }
+
+            lst = Expression::Index {
+                lst: Box::new(lst),
+                idx: Box::new(idx),
+                typ: *typ,
+            };
+        }
+
+        Ok(lst)
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expression<'a>> {
+        let Some(TokenDef {
+            tok: Token::Operador(ope),
+            pos,
+            ..
+        }) = self.peek()
+        else {
+            return self.parse_atom();
+        };
+
+        let Some(prec) = ope.unary_precedence() else {
+            return self.parse_atom();
+        };
+
+        let pos = pos.clone();
+        let ope = self.consume_operator()?;
+        let exp = self.parse_expression(prec)?;
+
+        self.parse_unop(exp, ope).map_err(|msg| SyntaxError {
+            pos,
+            msg,
+            ..Default::default()
+        })
+    }
+
+    fn parse_expression(&mut self, min_prec: u8) -> Result<Expression<'a>> {
+        let mut lhs = self.parse_prefix()?;
+
+        while let Some(def_ope) = self.peek() {
             let Token::Operador(ope) = &def_ope.tok else {
                 break;
             };
@@ -561,53 +1258,252 @@ impl<'a> Parser<'a> {
             let rhs = self.parse_expression(min_prec)?;
 
             lhs = if ope.is_atrib() {
-                self.parse_atrib(lhs, rhs, ope)
-                    .map_err(|msg| SyntaxError { pos, msg })?
+                self.parse_atrib(lhs, rhs, ope).map_err(|msg| SyntaxError {
+                    pos,
+                    msg,
+                    ..Default::default()
+                })?
             } else {
-                self.parse_binop(lhs, rhs, ope)
-                    .map_err(|msg| SyntaxError { pos, msg })?
+                self.parse_binop(lhs, rhs, ope).map_err(|msg| SyntaxError {
+                    pos,
+                    msg,
+                    ..Default::default()
+                })?
             }
         }
 
         Ok(lhs)
     }
 
-    fn parse_block(&mut self) -> Result<Block<'a>> {
+    fn parse_condition(&mut self) -> Result<Expression<'a>> {
+        let pos = self.peek().map(|la| la.pos.clone()).unwrap_or_default();
+        let exp = self.parse_expression(1)?;
+        if exp.get_type() != Type::Boolean {
+            let msg = "esperado expressão do tipo booleano".into();
+            Err(SyntaxError {
+                msg,
+                pos,
+                ..Default::default()
+            })?;
+        }
+        Ok(exp)
+    }
+
+    /// parses statements until `fim` or `senao`, leaving them to the caller
+    fn parse_body(&mut self) -> Result<Block<'a>> {
         let mut block = Block::new();
 
+        self.bodies += 1;
         self.enter_scope();
         while let Some(token) = self.peek() {
-            if token.tok == Token::Fim {
+            if matches!(token.tok, Token::Fim | Token::Senao) {
                 break;
             }
-            let stmt = self.parse_statement()?;
-            block.push_stmt(stmt);
+            if let Some(stmt) = self.parse_recovering() {
+                block.push_stmt(stmt);
+            }
         }
-        self.consume_invariant(Token::Fim)?;
         self.exit_scope();
 
+        if let Some(token) = self.peek() {
+            block.set_end(token.pos.clone());
+        }
+
         Ok(block)
     }
 
-    fn parse_program(&mut self) -> Result<Program<'a>> {
-        let mut block = Block::new();
+    fn parse_loop(&mut self) -> Result<Block<'a>> {
+        self.loops += 1;
+        let block = self.parse_block();
+        self.loops -= 1;
+        block
+    }
 
-        self.consume_invariant(Token::Programa)?;
-        let name = self.consume_identifier()?;
+    fn parse_block(&mut self) -> Result<Block<'a>> {
+        let block = self.parse_body()?;
+        self.consume_invariant(Token::Fim)?;
+        Ok(block)
+    }
 
-        while let Some(..) = self.peek() {
-            let stmt = self.parse_statement()?;
-            block.push_stmt(stmt);
+    /// parses a statement; on error, keeps it and skips to where the next statement
+    /// probably starts, so the errors after it are also found
+    fn parse_recovering(&mut self) -> Option<SyntaxTree<'a>> {
+        let remaining = self.tokens.len();
+        let bodies = self.bodies;
+        let opening = self.peek().map(|la| la.tok.clone()).filter(|tok| {
+            matches!(
+                tok,
+                Token::Se | Token::Enquanto | Token::Para | Token::Funcao
+            )
+        });
+
+        match self.parse_statement() {
+            Ok(stmt) => Some(stmt),
+            Err(err) => {
+                self.report(err);
+                if self.tokens.len() == remaining {
+                    self.next_token(); // the statement could not even start
+                }
+                self.synchronize();
+                let params = std::mem::take(&mut self.params);
+                match opening {
+                    Some(tok) if self.bodies == bodies => self.skip_block(&tok, params),
+                    _ => (),
+                }
+                None
+            }
         }
+    }
 
-        Ok(Program { name, block })
+    /// after an error in the header of a block opened by `tok`, parses its body
+    /// anyway, so its fim does not close the enclosing block. The body of a loop
+    /// or function is parsed inside one, with the `params` read before the error
+    fn skip_block(&mut self, tok: &Token, params: TokenTable<'a>) {
+        match tok {
+            Token::Para | Token::Enquanto => {
+                self.loops += 1;
+                self.skip_bodies();
+                self.loops -= 1;
+            }
+            Token::Funcao => {
+                // the rest of the header, like its return type, is not the body
+                let row = self.last.row;
+                while self
+                    .peek()
+                    .is_some_and(|la| la.pos.row == row && la.tok != Token::Fim)
+                {
+                    self.next_token();
+                }
+
+                // the return type is unknown, so a value after retorne is
+                // parsed as a statement of its own
+                let outer = std::mem::replace(&mut self.symbols, vec![params]);
+                let hidden = std::mem::replace(&mut self.hidden, outer);
+                let loops = std::mem::take(&mut self.loops);
+                let ret = self.ret.replace(Type::Void);
+                self.skip_bodies();
+                self.symbols = std::mem::replace(&mut self.hidden, hidden);
+                self.loops = loops;
+                self.ret = ret;
+            }
+            _ => self.skip_bodies(),
+        }
+    }
+
+    /// parses the bodies of a block up to its fim, the header of each senao se
+    /// being skipped like the first one
+    fn skip_bodies(&mut self) {
+        loop {
+            _ = self.parse_body();
+            match self.peek().map(|la| &la.tok) {
+                Some(Token::Senao) => {
+                    self.next_token();
+                    if let Some(Token::Se) = self.peek().map(|la| &la.tok) {
+                        self.next_token();
+                        self.synchronize();
+                    }
+                }
+                Some(Token::Fim) => {
+                    self.next_token();
+                    break;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// skips tokens until a keyword that starts a statement, `fim`, `senao`
+    /// or the first token of another line
+    fn synchronize(&mut self) {
+        let row = self.last.row;
+        while let Some(TokenDef { tok, pos, .. }) = self.peek() {
+            let sync = matches!(
+                tok,
+                Token::Seja
+                    | Token::Inteiro
+                    | Token::Real
+                    | Token::Texto
+                    | Token::Booleano
+                    | Token::Lista
+                    | Token::Se
+                    | Token::Senao
+                    | Token::Enquanto
+                    | Token::Para
+                    | Token::Pare
+                    | Token::Continue
+                    | Token::Funcao
+                    | Token::Retorne
+                    | Token::Fim
+            );
+            if sync || pos.row > row {
+                break;
+            }
+            self.next_token();
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Program<'a>> {
-        self.parse_program()
+    fn parse_program(&mut self) -> Program<'a> {
+        let mut block = Block::new();
+
+        let pos = self.peek().map(|la| la.pos.clone()).unwrap_or_default();
+        let name = self
+            .consume_invariant(Token::Programa)
+            .and_then(|_| self.consume_identifier())
+            .unwrap_or_else(|err| {
+                self.report(err);
+                self.synchronize();
+                ""
+            });
+
+        while self.peek().is_some() {
+            if let Some(stmt) = self.parse_recovering() {
+                block.push_stmt(stmt);
+            }
+        }
+
+        Program { pos, name, block }
+    }
+
+    pub fn parse(&mut self) -> std::result::Result<Program<'a>, SyntaxErrors<'a>> {
+        let program = self.parse_program();
+        if self.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(SyntaxErrors {
+                errors: std::mem::take(&mut self.errors),
+                program,
+            })
+        }
+    }
+
+    /// parses statements that follow the ones parsed before, keeping their declarations,
+    /// as the repl does. On error, the declarations made by `tokens` are discarded
+    pub fn parse_more(
+        &mut self,
+        tokens: Vec<TokenDef<'a>>,
+    ) -> std::result::Result<Block<'a>, Vec<SyntaxError>> {
+        let symbols = self.symbols.clone();
+        let functions = self.functions.clone();
+        self.tokens = tokens.into_iter().peekable();
+        self.ends.clear(); // each input starts again at the first line
+
+        let mut block = Block::new();
+        while self.peek().is_some() {
+            if let Some(stmt) = self.parse_recovering() {
+                block.push_stmt(stmt);
+            }
+        }
+
+        if !self.errors.is_empty() {
+            self.symbols = symbols;
+            self.functions = functions;
+            return Err(std::mem::take(&mut self.errors));
+        }
+
+        Ok(block)
     }
 }
 
-pub fn parse(tokens: Vec<TokenDef>) -> Result<Program> {
+pub fn parse(tokens: Vec<TokenDef<'_>>) -> std::result::Result<Program<'_>, SyntaxErrors<'_>> {
     Parser::new(tokens).parse()
 }
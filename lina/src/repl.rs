@@ -0,0 +1,81 @@
+use std::io::{Read, Write};
+
+use crate::compiler::{CompileError, Compiler};
+use crate::lexer::{lex, LexicalError};
+use crate::parser::{Parser, SyntaxError};
+use crate::token::{Token, TokenDef};
+use crate::vm::{LinaVm, VmError};
+
+pub enum ReplError {
+    LexicalError(LexicalError),
+    SyntaxError(Vec<SyntaxError>),
+    CompileError(CompileError),
+    RuntimeError(VmError),
+}
+
+/// runs code one input at a time, keeping the declarations of the previous inputs.
+///
+/// The parser and the compiler borrow the code for as long as the session lives,
+/// so every input is leaked, the same as the program would be for a whole run.
+pub struct Repl<In, Out>
+where
+    In: Read,
+    Out: Write,
+{
+    parser: Parser<'static>,
+    compiler: Compiler<'static>,
+    pub vm: LinaVm<In, Out>,
+}
+
+impl<In, Out> Repl<In, Out>
+where
+    In: Read,
+    Out: Write,
+{
+    pub fn new(stdin: In, stdout: Out) -> Self {
+        Self {
+            parser: Parser::new(Vec::new()),
+            compiler: Compiler::new(),
+            vm: LinaVm::empty(stdin, stdout),
+        }
+    }
+
+    pub fn eval(&mut self, code: String) -> Result<(), ReplError> {
+        let code: &'static str = Box::leak(code.into_boxed_str());
+
+        let tokens = lex(code).map_err(ReplError::LexicalError)?;
+        let block = self
+            .parser
+            .parse_more(tokens)
+            .map_err(ReplError::SyntaxError)?;
+        let block = Box::leak(Box::new(block));
+        let bytecode = self
+            .compiler
+            .compile_more(block)
+            .map_err(ReplError::CompileError)?;
+
+        self.vm.append(bytecode);
+        self.vm.run().map_err(ReplError::RuntimeError)
+    }
+}
+
+/// whether `code` opens blocks that are still waiting for their `fim`
+pub fn is_incomplete(code: &str) -> bool {
+    let Ok(tokens) = lex(code) else {
+        return false; // eval reports the error
+    };
+
+    let mut depth = 0;
+    let mut prev = None;
+    for TokenDef { tok, .. } in &tokens {
+        match tok {
+            Token::Se if prev == Some(&Token::Senao) => {} // senao se shares the fim
+            Token::Se | Token::Enquanto | Token::Para | Token::Funcao => depth += 1,
+            Token::Fim => depth -= 1,
+            _ => {}
+        }
+        prev = Some(tok);
+    }
+
+    depth > 0
+}
@@ -4,6 +4,7 @@ use crate::token::{Literal, Operador, TokenPos};
 
 #[derive(Debug)]
 pub struct Program<'a> {
+    pub pos: TokenPos,
     pub name: &'a str,
     pub block: Block<'a>,
 }
@@ -14,56 +15,150 @@ pub enum Type {
     Real,
     Text,
     Boolean,
+    List(Box<Type>),
     Void,
 }
 
+impl Type {
+    /// false if some part of the type is unknown, like the items of an empty list
+    pub fn is_complete(&self) -> bool {
+        match self {
+            Type::Void => false,
+            Type::List(typ) => typ.is_complete(),
+            _ => true,
+        }
+    }
+}
+
+/// how a variable was declared, which the formatter writes back
+#[derive(Debug, Clone, PartialEq)]
+pub enum Declaration {
+    Seja,    // seja x := 1
+    Typed,   // inteiro x := 1
+    Default, // inteiro x
+}
+
 #[derive(Debug)]
 pub enum SyntaxTree<'a> {
     Assign {
         pos: TokenPos,
+        dcl: Declaration,
         typ: Type,
         idt: &'a str,
         exp: Expression<'a>,
     },
-    Expr(Expression<'a>),
+    Expr {
+        pos: TokenPos,
+        exp: Expression<'a>,
+    },
     SeStmt {
+        pos: TokenPos,
         exp: Expression<'a>,
         blk: Block<'a>,
+        alt: Vec<(Expression<'a>, Block<'a>)>,
+        els: Option<Block<'a>>,
     },
     EnquantoStmt {
+        pos: TokenPos,
         exp: Expression<'a>,
         blk: Block<'a>,
     },
     ParaStmt {
+        pos: TokenPos,
         idt: &'a str,
-        sta: Option<Literal<'a>>,
+        sta: Option<(Literal<'a>, &'a str)>, // with how it was written
         lmt: Expression<'a>,
-        stp: Option<Literal<'a>>,
+        stp: Option<(Literal<'a>, &'a str)>,
+        blk: Block<'a>,
+    },
+    FuncaoStmt {
+        pos: TokenPos,
+        idt: &'a str,
+        par: Vec<(&'a str, Type)>,
+        ret: Type,
         blk: Block<'a>,
     },
+    RetorneStmt {
+        pos: TokenPos,
+        exp: Option<Expression<'a>>,
+    },
+    PareStmt {
+        pos: TokenPos,
+    },
+    ContinueStmt {
+        pos: TokenPos,
+    },
 }
 
-#[derive(Debug)]
+impl SyntaxTree<'_> {
+    /// position where the statement starts
+    pub fn pos(&self) -> &TokenPos {
+        match self {
+            SyntaxTree::Assign { pos, .. }
+            | SyntaxTree::Expr { pos, .. }
+            | SyntaxTree::SeStmt { pos, .. }
+            | SyntaxTree::EnquantoStmt { pos, .. }
+            | SyntaxTree::ParaStmt { pos, .. }
+            | SyntaxTree::FuncaoStmt { pos, .. }
+            | SyntaxTree::RetorneStmt { pos, .. }
+            | SyntaxTree::PareStmt { pos }
+            | SyntaxTree::ContinueStmt { pos } => pos,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Block<'a> {
     stmts: Vec<SyntaxTree<'a>>,
+    end: TokenPos, // position of the token that closes the block
 }
 
 impl<'a> Block<'a> {
     pub fn new() -> Self {
-        Self { stmts: Vec::new() }
+        Self {
+            stmts: Vec::new(),
+            end: TokenPos::default(),
+        }
     }
     pub fn push_stmt(&mut self, stmt: SyntaxTree<'a>) {
         self.stmts.push(stmt);
     }
 
-    pub fn iter_stmts(&self) -> std::slice::Iter<'_, SyntaxTree> {
+    pub fn set_end(&mut self, end: TokenPos) {
+        self.end = end;
+    }
+
+    pub fn end(&self) -> &TokenPos {
+        &self.end
+    }
+
+    pub fn iter_stmts(&self) -> std::slice::Iter<'_, SyntaxTree<'a>> {
         self.stmts.iter()
     }
+
+    /// removes every statement, leaving the block empty to be filled again
+    pub fn take_stmts(&mut self) -> Vec<SyntaxTree<'a>> {
+        std::mem::take(&mut self.stmts)
+    }
+
+    /// true if every path through the block reaches a `retorne`
+    pub fn returns(&self) -> bool {
+        self.iter_stmts().any(|stmt| match stmt {
+            SyntaxTree::RetorneStmt { .. } => true,
+            SyntaxTree::SeStmt {
+                blk,
+                alt,
+                els: Some(els),
+                ..
+            } => blk.returns() && alt.iter().all(|(_, blk)| blk.returns()) && els.returns(),
+            _ => false,
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression<'a> {
-    Literal(Literal<'a>),
+    Literal(Literal<'a>, Option<&'a str>), // with how it was written, if it was in the code
     Identifier(&'a str, Type),
     BinOp {
         typ: Type,
@@ -71,22 +166,41 @@ pub enum Expression<'a> {
         lhs: Box<Expression<'a>>,
         rhs: Box<Expression<'a>>,
     },
+    UnOp {
+        typ: Type,
+        ope: Operador,
+        exp: Box<Expression<'a>>,
+    },
     Cast(Box<Expression<'a>>, Type),
+    List {
+        exp: Vec<Expression<'a>>,
+        typ: Type,
+    },
+    Index {
+        lst: Box<Expression<'a>>,
+        idx: Box<Expression<'a>>,
+        typ: Type,
+    },
     Function {
         idt: &'a str,
         arg: Vec<Expression<'a>>,
         ret: Type,
     },
+    Interpolation(Vec<Expression<'a>>), // pieces of a text, all of type texto
 }
 
 impl<'a> Expression<'a> {
     pub fn get_type(&self) -> Type {
         match self {
-            Self::Literal(ltr) => Type::from(ltr),
+            Self::Literal(ltr, _) => Type::from(ltr),
             Self::Identifier(_, typ) => typ.clone(),
             Self::BinOp { typ, .. } => typ.clone(),
+            Self::UnOp { typ, .. } => typ.clone(),
             Self::Cast(_, typ) => typ.clone(),
+            Self::List { typ, .. } => typ.clone(),
+            Self::Index { typ, .. } => typ.clone(),
             Self::Function { ret, .. } => ret.clone(),
+            Self::Interpolation(..) => Type::Text,
         }
     }
 }
@@ -121,6 +235,7 @@ impl Display for Type {
             Type::Real => write!(f, "real"),
             Type::Text => write!(f, "texto"),
             Type::Boolean => write!(f, "booleano"),
+            Type::List(typ) => write!(f, "lista de {typ}"),
             Type::Void => write!(f, "vazio"),
         }
     }
@@ -129,20 +244,25 @@ impl Display for Type {
 impl<'a> Display for SyntaxTree<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SyntaxTree::Assign {
-                pos: _,
-                typ,
-                idt,
-                exp,
-            } => {
+            SyntaxTree::Assign { typ, idt, exp, .. } => {
                 writeln!(f, "{typ} {idt} := {exp}")
             }
-            SyntaxTree::SeStmt { exp, blk } => {
+            SyntaxTree::SeStmt {
+                exp, blk, alt, els, ..
+            } => {
                 writeln!(f, "se {exp} entao")?;
                 write!(f, "{blk}")?;
+                for (exp, blk) in alt {
+                    writeln!(f, "senao se {exp} entao")?;
+                    write!(f, "{blk}")?;
+                }
+                if let Some(els) = els {
+                    writeln!(f, "senao")?;
+                    write!(f, "{els}")?;
+                }
                 writeln!(f, "fim")
             }
-            SyntaxTree::EnquantoStmt { exp, blk } => {
+            SyntaxTree::EnquantoStmt { exp, blk, .. } => {
                 writeln!(f, "enquanto {exp} faca")?;
                 write!(f, "{blk}")?;
                 writeln!(f, "fim")
@@ -153,15 +273,39 @@ impl<'a> Display for SyntaxTree<'a> {
                 lmt,
                 stp,
                 blk,
+                ..
             } => {
-                let sta = sta.as_ref().unwrap_or(&Literal::Inteiro(0));
-                let stp = stp.as_ref().unwrap_or(&Literal::Inteiro(1));
+                let sta = sta.as_ref().map_or(&Literal::Inteiro(0), |(sta, _)| sta);
+                let stp = stp.as_ref().map_or(&Literal::Inteiro(1), |(stp, _)| stp);
                 writeln!(f, "para {idt} := {sta} ate {lmt} incremento {stp} repetir")?;
                 write!(f, "{blk}")?;
                 writeln!(f, "fim")
             }
-            SyntaxTree::Expr(expr) => {
-                writeln!(f, "{expr}")
+            SyntaxTree::FuncaoStmt {
+                idt, par, ret, blk, ..
+            } => {
+                let par_list = par
+                    .iter()
+                    .map(|(idt, typ)| format!("{typ} {idt}"))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                match ret {
+                    Type::Void => writeln!(f, "função {idt}({par_list})")?,
+                    _ => writeln!(f, "função {idt}({par_list}): {ret}")?,
+                }
+                write!(f, "{blk}")?;
+                writeln!(f, "fim")
+            }
+            SyntaxTree::RetorneStmt { exp: Some(exp), .. } => {
+                writeln!(f, "retorne {exp}")
+            }
+            SyntaxTree::RetorneStmt { exp: None, .. } => {
+                writeln!(f, "retorne")
+            }
+            SyntaxTree::PareStmt { .. } => writeln!(f, "pare"),
+            SyntaxTree::ContinueStmt { .. } => writeln!(f, "continue"),
+            SyntaxTree::Expr { exp, .. } => {
+                writeln!(f, "{exp}")
             }
         }
     }
@@ -184,14 +328,29 @@ impl<'a> Display for Block<'a> {
 impl<'a> Display for Expression<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Expression::Literal(literal) => write!(f, "{}", literal),
+            Expression::Literal(literal, _) => write!(f, "{}", literal),
             Expression::Identifier(idt, typ) => write!(f, "({typ}){idt}"),
             Expression::BinOp { ope, lhs, rhs, .. } => {
                 write!(f, "({} {} {})", lhs, ope, rhs)
             }
+            Expression::UnOp {
+                ope: Operador::Subt,
+                exp,
+                ..
+            } => write!(f, "(-{exp})"),
+            Expression::UnOp { ope, exp, .. } => write!(f, "({ope} {exp})"),
             Expression::Cast(exp, typ) => {
                 write!(f, "({typ}){exp}")
             }
+            Expression::List { exp, .. } => {
+                let exp_list = exp
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "[{exp_list}]")
+            }
+            Expression::Index { lst, idx, .. } => write!(f, "{lst}[{idx}]"),
             Expression::Function { idt, arg, .. } => {
                 let arg_list = arg
                     .iter()
@@ -200,6 +359,14 @@ impl<'a> Display for Expression<'a> {
                     .join(", ");
                 write!(f, "{idt}({arg_list})")
             }
+            Expression::Interpolation(exp) => {
+                let exp_list = exp
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>()
+                    .join(" + ");
+                write!(f, "({exp_list})")
+            }
         }
     }
 }
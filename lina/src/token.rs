@@ -1,15 +1,26 @@
+use std::borrow::Cow;
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct TokenPos {
     pub col: usize,
     pub row: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TokenDef<'a> {
     pub tok: Token<'a>,
     pub pos: TokenPos,
+    pub end: TokenPos, // position right after the token
+    pub src: &'a str,  // the token as written in the code
+}
+
+/// a comment, kept apart from the tokens so tools can put it back in the source
+#[derive(Debug, Clone)]
+pub struct Comment<'a> {
+    pub text: &'a str, // including the # or /* */ markers
+    pub pos: TokenPos,
+    pub block: bool,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -20,17 +31,23 @@ pub enum Token<'a> {
     Real,
     Texto,
     Booleano,
+    Lista,
+    De,
     Se,
     Entao,
+    Senao,
     Enquanto,
     Para,
     Ate,
     Incremento,
     Repetir,
+    Pare,
+    Continue,
     Funcao,
     Retorne,
     Identificador(&'a str),
     Literal(Literal<'a>),
+    Interpolacao(Vec<Parte<'a>>), // text with expressions between { and }
     Operador(Operador),
     Delimitador(Delimitador),
 
@@ -48,21 +65,36 @@ impl Display for Token<'_> {
             Token::Real => write!(f, "real"),
             Token::Texto => write!(f, "texto"),
             Token::Booleano => write!(f, "booleano"),
+            Token::Lista => write!(f, "lista"),
+            Token::De => write!(f, "de"),
 
             Token::Se => write!(f, "se"),
             Token::Entao => write!(f, "então"),
+            Token::Senao => write!(f, "senao"),
 
             Token::Enquanto => write!(f, "enquanto"),
             Token::Para => write!(f, "para"),
             Token::Ate => write!(f, "ate"),
             Token::Incremento => write!(f, "incremento"),
             Token::Repetir => write!(f, "repetir"),
+            Token::Pare => write!(f, "pare"),
+            Token::Continue => write!(f, "continue"),
 
             Token::Funcao => write!(f, "função"),
             Token::Retorne => write!(f, "retorne"),
 
             Token::Identificador(idt) => write!(f, "idetificador: {idt}"),
             Token::Literal(val) => write!(f, "valor: {val}"),
+            Token::Interpolacao(parts) => {
+                write!(f, "interpolação: ")?;
+                for part in parts {
+                    match part {
+                        Parte::Texto(text, _) => write!(f, "{text}")?,
+                        Parte::Expressao(tokens) => write!(f, "{{{} tokens}}", tokens.len())?,
+                    }
+                }
+                Ok(())
+            }
             Token::Operador(ope) => write!(f, "operador: {ope}"),
             Token::Delimitador(del) => write!(f, "delimitador: {del}"),
 
@@ -82,6 +114,7 @@ pub enum Operador {
     Diferente,
     E,
     Ou,
+    Nao,
 
     // Operadores Aritméticos
     Adic,
@@ -123,6 +156,9 @@ impl Operador {
             Operador::E => OpInfo(3, OpAssoc::L),
             Operador::Ou => OpInfo(2, OpAssoc::L),
 
+            // prefix only, never taken as a binary operator
+            Operador::Nao => OpInfo(0, OpAssoc::R),
+
             Operador::Atrib
             | Operador::AdicAtrib
             | Operador::SubtAtrib
@@ -133,6 +169,15 @@ impl Operador {
         }
     }
 
+    /// precedence of the operand when used as a prefix operator
+    pub fn unary_precedence(&self) -> Option<u8> {
+        match self {
+            Operador::Subt => Some(8), // -x ^ 2 is -(x ^ 2)
+            Operador::Nao => Some(4),  // nao x = y is nao (x = y)
+            _ => None,
+        }
+    }
+
     pub fn is_atrib(&self) -> bool {
         self >= &Operador::Atrib
     }
@@ -149,6 +194,7 @@ impl Display for Operador {
             Operador::Diferente => write!(f, "<>"),
             Operador::E => write!(f, "e"),
             Operador::Ou => write!(f, "ou"),
+            Operador::Nao => write!(f, "nao"),
 
             Operador::Adic => write!(f, "+"),
             Operador::Subt => write!(f, "-"),
@@ -170,12 +216,19 @@ impl Display for Operador {
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Literal<'a> {
-    Decimal(f32),
-    Inteiro(i32),
-    Texto(&'a str),
+    Decimal(f64),
+    Inteiro(i64),
+    Texto(Cow<'a, str>), // borrowed from the code, unless it has escape sequences
     Booleano(bool),
 }
 
+/// a piece of an interpolated text, like `"fib({i}) = {x}"`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Parte<'a> {
+    Texto(Cow<'a, str>, &'a str), // the text and how it was written, with its escapes
+    Expressao(Vec<TokenDef<'a>>), // tokens after the {, up to the }
+}
+
 impl Display for Literal<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -198,6 +251,7 @@ pub enum Delimitador {
 
     Virgula,
     PontoVirgula,
+    DoisPontos,
 }
 
 impl Display for Delimitador {
@@ -211,6 +265,7 @@ impl Display for Delimitador {
             Delimitador::FColch => write!(f, "]"),
             Delimitador::Virgula => write!(f, ","),
             Delimitador::PontoVirgula => write!(f, ";"),
+            Delimitador::DoisPontos => write!(f, ":"),
         }
     }
 }
@@ -0,0 +1,409 @@
+//! Checks bytecode before it runs, so a corrupted or hand made `.linac` file
+//! is refused instead of making the vm panic.
+//!
+//! The code is decoded from start to end, then every path of the main program
+//! and of each called function is followed keeping how many values are on the
+//! operand stack and how many variables the frame has.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+
+use crate::compiler::ByteCode;
+use crate::vm::{OpCode, MAX_SLOTS, WIDE_SIZE};
+
+#[derive(Debug, PartialEq)]
+pub enum InvalidCode {
+    Opcode(u8),
+    Prefixo,
+    Incompleta,
+    Constante { index: usize, len: usize },
+    Salto(isize),
+    Chamada(usize),
+    Argumentos { argc: usize, expected: usize },
+    Variavel { slot: usize, size: usize },
+    Alocacao(usize),
+    PilhaVazia { depth: usize, needed: usize },
+    PilhaInconsistente { depth: usize, expected: usize },
+    RetornoMisto,
+    RetornoForaDeFuncao,
+    SemFim,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VerifyError {
+    pub offset: usize, // start of the instruction at fault
+    pub err: InvalidCode,
+}
+
+impl Display for InvalidCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidCode::Opcode(byte) => write!(f, "{byte} não é um opcode conhecido"),
+            InvalidCode::Prefixo => {
+                write!(f, "WIDE deve vir antes de uma instrução com operandos")
+            }
+            InvalidCode::Incompleta => write!(f, "a instrução termina antes dos seus operandos"),
+            InvalidCode::Constante { index, len } => {
+                write!(f, "constante {index} não existe, há {len} constantes")
+            }
+            InvalidCode::Salto(target) => {
+                write!(
+                    f,
+                    "o salto para {target} não cai no início de uma instrução"
+                )
+            }
+            InvalidCode::Chamada(target) => {
+                write!(
+                    f,
+                    "a chamada para {target:#06x} não cai no início de uma função"
+                )
+            }
+            InvalidCode::Argumentos { argc, expected } => write!(
+                f,
+                "a função é chamada com {argc} argumentos aqui e com {expected} antes"
+            ),
+            InvalidCode::Variavel { slot, size } => {
+                write!(f, "variável {slot} fora das {size} alocadas")
+            }
+            InvalidCode::Alocacao(size) => {
+                write!(f, "ALLOC de {size} variáveis, o máximo é {MAX_SLOTS}")
+            }
+            InvalidCode::PilhaVazia { depth, needed } => write!(
+                f,
+                "a instrução usa {needed} valores da pilha, mas ela tem {depth}"
+            ),
+            InvalidCode::PilhaInconsistente { depth, expected } => write!(
+                f,
+                "a pilha chega com {depth} valores por um caminho e com {expected} por outro"
+            ),
+            InvalidCode::RetornoMisto => {
+                write!(
+                    f,
+                    "a função retorna um valor por um caminho e nada por outro"
+                )
+            }
+            InvalidCode::RetornoForaDeFuncao => write!(f, "retorno fora de uma função"),
+            InvalidCode::SemFim => write!(f, "o código termina sem HALT ou retorno"),
+        }
+    }
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} em {:#06x}", self.err, self.offset)
+    }
+}
+
+type Result<T> = std::result::Result<T, VerifyError>;
+
+fn error<T>(offset: usize, err: InvalidCode) -> Result<T> {
+    Err(VerifyError { offset, err })
+}
+
+/// one decoded instruction
+struct Instr {
+    op: OpCode,
+    args: [usize; 2], // operands, the jump target instead of the offset
+    next: usize,      // offset of the instruction after it
+}
+
+/// what is known when a path reaches an instruction
+#[derive(Clone, Copy)]
+struct State {
+    depth: usize, // values on the operand stack, counted from the start of the frame
+    slots: usize, // variables of the frame
+}
+
+/// values `op` takes from the stack and values it leaves there,
+/// except for calls, which depend on the called function
+fn effect(op: OpCode, args: [usize; 2]) -> (usize, usize) {
+    match op {
+        OpCode::Halt
+        | OpCode::Wide
+        | OpCode::Jmp
+        | OpCode::Alloc
+        | OpCode::Call
+        | OpCode::ReturnV => (0, 0),
+        OpCode::Const | OpCode::Load | OpCode::ReadL | OpCode::ReadI | OpCode::ReadF => (0, 1),
+        OpCode::Pop | OpCode::Write | OpCode::Store | OpCode::JmpT | OpCode::JmpF => (1, 0),
+        OpCode::Return => (1, 0),
+        OpCode::Dup => (1, 2),
+        OpCode::Dup2 => (2, 4),
+        OpCode::CastI | OpCode::CastF | OpCode::CastS | OpCode::Neg | OpCode::Not | OpCode::Len => {
+            (1, 1)
+        }
+        OpCode::Add
+        | OpCode::Sub
+        | OpCode::Mul
+        | OpCode::Div
+        | OpCode::Rem
+        | OpCode::Pow
+        | OpCode::Concat
+        | OpCode::Or
+        | OpCode::And
+        | OpCode::Eq
+        | OpCode::NE
+        | OpCode::LT
+        | OpCode::GT
+        | OpCode::LE
+        | OpCode::GE
+        | OpCode::Index
+        | OpCode::Remove => (2, 1),
+        OpCode::Append => (2, 0),
+        OpCode::SetIndex => (3, 1),
+        OpCode::List => (args[0], 1),
+    }
+}
+
+/// checks that `code` can run without breaking the vm: every opcode is known,
+/// operands are in range, jumps and calls land on instructions and each
+/// instruction finds the same stack depth whatever the path taken to it
+pub fn verify(code: &ByteCode) -> Result<()> {
+    let instrs = decode(code)?;
+    if instrs.is_empty() {
+        return error(0, InvalidCode::SemFim);
+    }
+
+    // every call of a function must pass the same number of arguments
+    let mut functions = BTreeMap::new();
+    for (&offset, instr) in &instrs {
+        if instr.op != OpCode::Call {
+            continue;
+        }
+
+        let [target, argc] = instr.args;
+        if target == 0 || !instrs.contains_key(&target) {
+            return error(offset, InvalidCode::Chamada(target));
+        }
+        match functions.insert(target, argc) {
+            Some(expected) if expected != argc => {
+                return error(offset, InvalidCode::Argumentos { argc, expected });
+            }
+            _ => {}
+        }
+    }
+
+    let mut returns = HashMap::new();
+    for &target in functions.keys() {
+        returns.insert(target, returns_value(&instrs, target)?);
+    }
+    if let Some(offset) = returns_at(&instrs, 0)?.into_iter().next() {
+        return error(offset, InvalidCode::RetornoForaDeFuncao);
+    }
+
+    check_stack(&instrs, &returns, 0, 0)?;
+    for (&target, &argc) in &functions {
+        check_stack(&instrs, &returns, target, argc)?;
+    }
+
+    Ok(())
+}
+
+/// reads every instruction, checking opcodes, constants and jump targets
+fn decode(code: &ByteCode) -> Result<BTreeMap<usize, Instr>> {
+    let bytes = &code.bytecode;
+    let mut instrs = BTreeMap::new();
+    let mut jumps = Vec::new();
+
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let byte = bytes[offset];
+        let Ok(mut op) = OpCode::try_from(byte) else {
+            return error(offset, InvalidCode::Opcode(byte));
+        };
+
+        let wide = op == OpCode::Wide;
+        let mut next = offset + 1;
+        if wide {
+            let Some(&byte) = bytes.get(next) else {
+                return error(offset, InvalidCode::Incompleta);
+            };
+            match OpCode::try_from(byte) {
+                Ok(prefixed) if !prefixed.operands().is_empty() => op = prefixed,
+                Ok(_) => return error(offset, InvalidCode::Prefixo),
+                Err(_) => return error(next, InvalidCode::Opcode(byte)),
+            }
+            next += 1;
+        }
+
+        // the bytes of the last operand are kept, a jump reads them as signed
+        let mut args = [0; 2];
+        let mut raw: &[u8] = &[];
+        for (arg, &size) in args.iter_mut().zip(op.operands()) {
+            let size = if wide { WIDE_SIZE } else { size };
+            let Some(operand) = bytes.get(next..next + size) else {
+                return error(offset, InvalidCode::Incompleta);
+            };
+
+            let mut value = [0; WIDE_SIZE];
+            value[..size].copy_from_slice(operand);
+            *arg = u32::from_le_bytes(value) as usize;
+            raw = operand;
+            next += size;
+        }
+
+        match op {
+            OpCode::Const if args[0] >= code.constants.len() => {
+                let len = code.constants.len();
+                return error(
+                    offset,
+                    InvalidCode::Constante {
+                        index: args[0],
+                        len,
+                    },
+                );
+            }
+            OpCode::Alloc if args[0] > MAX_SLOTS => {
+                return error(offset, InvalidCode::Alocacao(args[0]));
+            }
+            OpCode::Jmp | OpCode::JmpT | OpCode::JmpF => {
+                let jump = match raw.try_into() {
+                    Ok(narrow) => i16::from_le_bytes(narrow) as isize,
+                    Err(_) => i32::from_le_bytes(raw.try_into().expect("wide jumps have 4 bytes"))
+                        as isize,
+                };
+                let target = next as isize + jump;
+                jumps.push((offset, target));
+                args[0] = usize::try_from(target).unwrap_or(usize::MAX);
+            }
+            _ => {}
+        }
+
+        instrs.insert(offset, Instr { op, args, next });
+        offset = next;
+    }
+
+    for (offset, target) in jumps {
+        let landed = usize::try_from(target).is_ok_and(|target| instrs.contains_key(&target));
+        if !landed {
+            return error(offset, InvalidCode::Salto(target));
+        }
+    }
+
+    Ok(instrs)
+}
+
+/// instructions that may run after `instr`, which starts at `offset`
+fn successors(instrs: &BTreeMap<usize, Instr>, offset: usize) -> Result<Vec<usize>> {
+    let instr = &instrs[&offset];
+    let next = match instr.op {
+        OpCode::Halt | OpCode::Return | OpCode::ReturnV => return Ok(vec![]),
+        OpCode::Jmp => return Ok(vec![instr.args[0]]),
+        OpCode::JmpT | OpCode::JmpF => vec![instr.next, instr.args[0]],
+        _ => vec![instr.next],
+    };
+
+    if !instrs.contains_key(&instr.next) {
+        return error(offset, InvalidCode::SemFim);
+    }
+    Ok(next)
+}
+
+/// the returns reachable from `entry`, which are the ends of a function
+fn returns_at(instrs: &BTreeMap<usize, Instr>, entry: usize) -> Result<Vec<usize>> {
+    let mut seen = HashSet::from([entry]);
+    let mut pending = vec![entry];
+    let mut returns = Vec::new();
+
+    while let Some(offset) = pending.pop() {
+        if matches!(instrs[&offset].op, OpCode::Return | OpCode::ReturnV) {
+            returns.push(offset);
+        }
+        for next in successors(instrs, offset)? {
+            if seen.insert(next) {
+                pending.push(next);
+            }
+        }
+    }
+
+    returns.sort();
+    Ok(returns)
+}
+
+/// whether the function at `entry` leaves a value for its caller
+fn returns_value(instrs: &BTreeMap<usize, Instr>, entry: usize) -> Result<bool> {
+    let returns = returns_at(instrs, entry)?;
+    let Some(first) = returns.first() else {
+        return Ok(false); // never returns, so its calls leave nothing
+    };
+
+    let value = instrs[first].op == OpCode::Return;
+    match returns
+        .iter()
+        .find(|&&offset| (instrs[&offset].op == OpCode::Return) != value)
+    {
+        Some(&offset) => error(offset, InvalidCode::RetornoMisto),
+        None => Ok(value),
+    }
+}
+
+/// follows every path from `entry`, whose frame starts with `argc` variables
+fn check_stack(
+    instrs: &BTreeMap<usize, Instr>,
+    returns: &HashMap<usize, bool>,
+    entry: usize,
+    argc: usize,
+) -> Result<()> {
+    let mut states = HashMap::new();
+    states.insert(
+        entry,
+        State {
+            depth: 0,
+            slots: argc,
+        },
+    );
+    let mut pending = vec![entry];
+
+    while let Some(offset) = pending.pop() {
+        let State { depth, mut slots } = states[&offset];
+        let instr = &instrs[&offset];
+
+        let (pops, pushes) = match instr.op {
+            OpCode::Call => (instr.args[1], returns[&instr.args[0]] as usize),
+            op => effect(op, instr.args),
+        };
+        if depth < pops {
+            return error(
+                offset,
+                InvalidCode::PilhaVazia {
+                    depth,
+                    needed: pops,
+                },
+            );
+        }
+
+        match instr.op {
+            OpCode::Alloc => slots = slots.max(instr.args[0]),
+            OpCode::Load | OpCode::Store if instr.args[0] >= slots => {
+                let slot = instr.args[0];
+                return error(offset, InvalidCode::Variavel { slot, size: slots });
+            }
+            _ => {}
+        }
+
+        let state = State {
+            depth: depth - pops + pushes,
+            slots,
+        };
+        for next in successors(instrs, offset)? {
+            match states.get_mut(&next) {
+                None => {
+                    states.insert(next, state);
+                    pending.push(next);
+                }
+                Some(known) if known.depth != state.depth => {
+                    let (depth, expected) = (state.depth, known.depth);
+                    return error(next, InvalidCode::PilhaInconsistente { depth, expected });
+                }
+                // a variable is only allocated if every path allocated it
+                Some(known) if known.slots > state.slots => {
+                    known.slots = state.slots;
+                    pending.push(next);
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,15 +1,37 @@
+use std::cell::RefCell;
 use std::fmt::Display;
 use std::io::{Read, Write};
+use std::rc::Rc;
 
 use crate::compiler::ByteCode;
+use crate::token::TokenPos;
+use crate::verifier::{verify, VerifyError};
+
+/// operands are little-endian with the same width on every host, so bytecode built
+/// natively also runs on wasm32, see `linac`. Most of them take one or two bytes,
+/// and a WIDE prefix makes every operand of the next instruction take `WIDE_SIZE`
+pub const SLOT_SIZE: usize = 1; // variables, list lengths and argument counts
+pub const CONST_SIZE: usize = 2; // indexes of the constant pool
+pub const JUMP_SIZE: usize = 2; // jump offsets, as i16
+pub const ADDR_SIZE: usize = 4; // function addresses and frame sizes, only known after emitted
+pub const WIDE_SIZE: usize = 4; // any operand after WIDE, as u32 or i32 for jumps
+
+/// calls that may be running at once, so a recursion without end stops with an
+/// error instead of taking all the memory of the process
+pub const MAX_CALLS: usize = 10_000;
+
+/// variables a frame may allocate, so a corrupted ALLOC is refused by the verifier
+/// instead of asking for more memory than there is
+pub const MAX_SLOTS: usize = 4096;
 
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OpCode {
     Halt = 0x0,
 
     Const,
     Dup,
+    Dup2,
     Pop,
 
     CastI,
@@ -21,16 +43,19 @@ pub enum OpCode {
     Mul,
     Div,
     Rem,
+    Pow,
 
     Concat,
 
     Or,
     And,
 
+    Neg,
+    Not,
+
     // TODO
     // Xor,
     // Shl,
-    // Neg,
     Jmp,
     JmpT,
     JmpF,
@@ -48,11 +73,22 @@ pub enum OpCode {
 
     Write,
 
+    Alloc,
     Load,
     Store,
 
+    List,
+    Index,
+    SetIndex,
+    Len,
+    Append,
+    Remove,
+
     Call,
     Return,
+    ReturnV,
+
+    Wide,
 }
 
 impl Display for OpCode {
@@ -61,6 +97,7 @@ impl Display for OpCode {
             OpCode::Halt => write!(f, "HALT"),
             OpCode::Const => write!(f, "CONST"),
             OpCode::Dup => write!(f, "DUP"),
+            OpCode::Dup2 => write!(f, "DUP2"),
             OpCode::Pop => write!(f, "POP"),
             OpCode::CastI => write!(f, "CASTI"),
             OpCode::CastF => write!(f, "CASTF"),
@@ -70,9 +107,12 @@ impl Display for OpCode {
             OpCode::Mul => write!(f, "MUL"),
             OpCode::Div => write!(f, "DIV"),
             OpCode::Rem => write!(f, "REM"),
+            OpCode::Pow => write!(f, "POW"),
             OpCode::Concat => write!(f, "CONCAT"),
             OpCode::Or => write!(f, "OR"),
             OpCode::And => write!(f, "AND"),
+            OpCode::Neg => write!(f, "NEG"),
+            OpCode::Not => write!(f, "NOT"),
             OpCode::Jmp => write!(f, "JMP"),
             OpCode::JmpT => write!(f, "JMPT"),
             OpCode::JmpF => write!(f, "JMPF"),
@@ -86,10 +126,19 @@ impl Display for OpCode {
             OpCode::ReadI => write!(f, "READI"),
             OpCode::ReadF => write!(f, "READF"),
             OpCode::Write => write!(f, "WRITE"),
+            OpCode::Alloc => write!(f, "ALLOC"),
             OpCode::Load => write!(f, "LOAD"),
             OpCode::Store => write!(f, "STORE"),
+            OpCode::List => write!(f, "LIST"),
+            OpCode::Index => write!(f, "INDEX"),
+            OpCode::SetIndex => write!(f, "SETINDEX"),
+            OpCode::Len => write!(f, "LEN"),
+            OpCode::Append => write!(f, "APPEND"),
+            OpCode::Remove => write!(f, "REMOVE"),
             OpCode::Call => write!(f, "CALL"),
             OpCode::Return => write!(f, "RETURN"),
+            OpCode::ReturnV => write!(f, "RETURNV"),
+            OpCode::Wide => write!(f, "WIDE"),
         }
     }
 }
@@ -113,21 +162,38 @@ impl TryFrom<u8> for OpCode {
     type Error = CodeError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        if value < OpCode::Halt as u8 || value > OpCode::Return as u8 {
+        if value > OpCode::Wide as u8 {
             Err(format!("{value} não é um opcode conhecido").into())
         } else {
-            Ok(unsafe { std::mem::transmute(value) })
+            Ok(unsafe { std::mem::transmute::<u8, OpCode>(value) })
+        }
+    }
+}
+
+impl OpCode {
+    /// size of each operand that follows the opcode, without a WIDE prefix
+    pub fn operands(self) -> &'static [usize] {
+        match self {
+            OpCode::Const => &[CONST_SIZE],
+            OpCode::Load | OpCode::Store | OpCode::List => &[SLOT_SIZE],
+            OpCode::Jmp | OpCode::JmpT | OpCode::JmpF => &[JUMP_SIZE],
+            OpCode::Alloc => &[ADDR_SIZE],
+            OpCode::Call => &[ADDR_SIZE, SLOT_SIZE],
+            _ => &[],
         }
     }
 }
 
+pub type LinaList = Rc<RefCell<Vec<LinaValue>>>;
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum LinaValue {
-    Int32(i32),
-    Float32(f32),
+    Int64(i64),
+    Float64(f64),
     String(String),
     Address(usize),
     Boolean(bool),
+    List(LinaList), // shared, so assigning a list does not copy it
 }
 
 impl Default for LinaValue {
@@ -156,37 +222,37 @@ impl Display for TypeError {
     }
 }
 
-impl TryInto<i32> for LinaValue {
+impl TryInto<i64> for LinaValue {
     type Error = TypeError;
 
-    fn try_into(self) -> Result<i32, Self::Error> {
+    fn try_into(self) -> Result<i64, Self::Error> {
         match self {
-            Self::Int32(number) => Ok(number),
-            _ => Err(format!("esperado i32, obteve {self}").into()),
+            Self::Int64(number) => Ok(number),
+            _ => Err(format!("esperado i64, obteve {self}").into()),
         }
     }
 }
 
-impl From<i32> for LinaValue {
-    fn from(value: i32) -> Self {
-        LinaValue::Int32(value)
+impl From<i64> for LinaValue {
+    fn from(value: i64) -> Self {
+        LinaValue::Int64(value)
     }
 }
 
-impl TryInto<f32> for LinaValue {
+impl TryInto<f64> for LinaValue {
     type Error = TypeError;
 
-    fn try_into(self) -> Result<f32, Self::Error> {
+    fn try_into(self) -> Result<f64, Self::Error> {
         match self {
-            Self::Float32(number) => Ok(number),
-            _ => Err(format!("esperado f32, obteve {self}").into()),
+            Self::Float64(number) => Ok(number),
+            _ => Err(format!("esperado f64, obteve {self}").into()),
         }
     }
 }
 
-impl From<f32> for LinaValue {
-    fn from(value: f32) -> Self {
-        LinaValue::Float32(value)
+impl From<f64> for LinaValue {
+    fn from(value: f64) -> Self {
+        LinaValue::Float64(value)
     }
 }
 
@@ -241,19 +307,52 @@ impl From<usize> for LinaValue {
     }
 }
 
+impl TryInto<LinaList> for LinaValue {
+    type Error = TypeError;
+
+    fn try_into(self) -> Result<LinaList, Self::Error> {
+        match self {
+            Self::List(list) => Ok(list),
+            _ => Err(format!("esperado lista, obteve {self}").into()),
+        }
+    }
+}
+
+impl From<Vec<LinaValue>> for LinaValue {
+    fn from(value: Vec<LinaValue>) -> Self {
+        LinaValue::List(Rc::new(RefCell::new(value)))
+    }
+}
+
 impl Display for LinaValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LinaValue::Int32(value) => value.fmt(f),
-            LinaValue::Float32(value) => value.fmt(f),
+            LinaValue::Int64(value) => value.fmt(f),
+            LinaValue::Float64(value) => value.fmt(f),
             LinaValue::String(value) => value.fmt(f),
             LinaValue::Boolean(value) => value.fmt(f),
             LinaValue::Address(value) => write!(f, "{value:#02x}"),
+            LinaValue::List(list) => {
+                let items = list
+                    .borrow()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "[{items}]")
+            }
         }
     }
 }
 
 pub enum RuntimeError {
+    IndiceInvalido { idx: i64, len: usize },
+    ExpoenteNegativo(i64),
+    Estouro(String),
+    DivisaoPorZero(String),
+    SemValor,
+    EstouroDePilha,
+    SemMemoria,
     CodeError(CodeError),
     TypeError(TypeError),
     IoError(std::io::Error),
@@ -301,6 +400,30 @@ impl From<std::num::ParseFloatError> for RuntimeError {
 impl Display for RuntimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            RuntimeError::IndiceInvalido { idx, len } => {
+                write!(f, "índice {idx} fora dos limites da lista de tamanho {len}")
+            }
+            RuntimeError::ExpoenteNegativo(exp) => {
+                write!(
+                    f,
+                    "expoente negativo {exp} em potência de inteiros, use um expoente real"
+                )
+            }
+            RuntimeError::Estouro(ope) => {
+                write!(f, "o resultado de {ope} não cabe em um inteiro")
+            }
+            RuntimeError::DivisaoPorZero(ope) => {
+                write!(f, "divisão por zero em {ope}")
+            }
+            RuntimeError::SemValor => {
+                write!(f, "variável sem valor, a sua declaração não terminou")
+            }
+            RuntimeError::EstouroDePilha => {
+                write!(f, "mais de {MAX_CALLS} chamadas de função em andamento")
+            }
+            RuntimeError::SemMemoria => {
+                write!(f, "não há memória para as variáveis da função")
+            }
             RuntimeError::CodeError(err) => write!(f, "{err}"),
             RuntimeError::TypeError(err) => write!(f, "{err}"),
             RuntimeError::IoError(err) => write!(f, "{err}"),
@@ -311,9 +434,23 @@ impl Display for RuntimeError {
     }
 }
 
-#[derive(PartialEq, Eq)]
+/// runtime error with the position of the statement that caused it
+pub struct VmError {
+    pub pos: TokenPos,
+    pub err: RuntimeError,
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Erro de execução: {}", self.err)?;
+        writeln!(f, "Posição -> {}:{}", self.pos.row, self.pos.col)
+    }
+}
+
+#[derive(PartialEq, Eq, Default)]
 pub enum VmState {
-    Idle,      // vm is ready to start
+    #[default]
+    Idle, // vm is ready to start
     Executing, // while this, execute the bytecode
     WillRead,  // next instruction is to read
     WillWrite, // next instruction is to write
@@ -330,12 +467,6 @@ impl Display for VmState {
     }
 }
 
-impl Default for VmState {
-    fn default() -> Self {
-        VmState::Idle
-    }
-}
-
 type VmResult<T> = Result<T, RuntimeError>;
 
 macro_rules! binop {
@@ -355,17 +486,58 @@ macro_rules! binop {
     }};
 }
 
+/// like `binop!`, but integers use the checked operation `$checked`,
+/// reporting division by zero and overflow instead of panicking or wrapping
+macro_rules! checked_binop {
+    ($s:ident, $op:tt, $checked:ident $(, $i:ident)*) => {{
+        let rhs = $s.pop();
+        let lhs = $s.pop();
+
+        let result = match (&lhs, &rhs) {
+            (LinaValue::Int64(lhs), LinaValue::Int64(rhs)) => {
+                // the operation is only written out when it fails
+                let ope = || format!("{lhs} {} {rhs}", stringify!($op));
+                if matches!(stringify!($op), "/" | "%") && *rhs == 0 {
+                    return Err(RuntimeError::DivisaoPorZero(ope()));
+                }
+                lhs.$checked(*rhs).ok_or_else(|| RuntimeError::Estouro(ope()))?.into()
+            }
+            (LinaValue::Address(lhs), LinaValue::Address(rhs)) => {
+                let ope = || format!("{lhs} {} {rhs}", stringify!($op));
+                lhs.$checked(*rhs).ok_or_else(|| RuntimeError::Estouro(ope()))?.into()
+            }
+            $((LinaValue::$i(lhs), LinaValue::$i(rhs)) => (lhs $op rhs).into(),)*
+            _ => {
+                let msg = format!("tipos incompatíveis para a operação {}: lhs: {:?}, rhs: {:?}", stringify!($op), lhs, rhs);
+                return Err(TypeError(msg).into());
+            },
+        };
+
+        $s.push(result);
+    }};
+}
+
+struct Frame {
+    ret: usize, // address to resume the caller
+    fp: usize,  // frame pointer of the caller
+    sp: usize,  // height of the operand stack without the arguments
+}
+
 pub struct LinaVm<In, Out>
 where
     In: Read,
     Out: Write,
 {
-    bytecode: Vec<u8>,         // bytecode to be executed
-    constants: Vec<LinaValue>, // constant pool
-    pc: usize,                 // program counter
-    stack: Vec<LinaValue>,     // operand stack
-    pub stdin: In,             // standard input
-    pub stdout: Out,           // standard output
+    bytecode: Vec<u8>,              // bytecode to be executed
+    constants: Vec<LinaValue>,      // constant pool
+    lines: Vec<(usize, TokenPos)>,  // statement of each bytecode offset
+    pc: usize,                      // program counter
+    fp: usize,                      // frame pointer, where the variables of the current call start
+    stack: Vec<LinaValue>,          // operand stack
+    locals: Vec<Option<LinaValue>>, // variables of every call, None until assigned
+    frames: Vec<Frame>,             // call stack
+    pub stdin: In,                  // standard input
+    pub stdout: Out,                // standard output
 }
 
 impl<In, Out> LinaVm<In, Out>
@@ -373,38 +545,89 @@ where
     In: Read,
     Out: Write,
 {
-    pub fn new(code: ByteCode, stdin: In, stdout: Out) -> Self {
-        Self {
+    /// a vm ready to run `code`, if it passes the verifier
+    pub fn new(code: ByteCode, stdin: In, stdout: Out) -> Result<Self, VerifyError> {
+        verify(&code)?;
+        Ok(Self {
             bytecode: code.bytecode,
             constants: code.constants,
+            lines: code.lines,
             pc: 0,
+            fp: 0,
             stack: Vec::with_capacity(512),
+            locals: Vec::with_capacity(512),
+            frames: Vec::new(),
             stdin,
             stdout,
-        }
+        })
     }
 
     pub fn empty(stdin: In, stdout: Out) -> Self {
         Self {
             bytecode: Vec::default(),
             constants: Vec::default(),
+            lines: Vec::default(),
             pc: 0,
+            fp: 0,
             stack: Vec::with_capacity(512),
+            locals: Vec::with_capacity(512),
+            frames: Vec::new(),
             stdin,
             stdout,
         }
     }
 
-    pub fn start(&mut self, code: ByteCode) {
+    /// replaces the code being run by `code`, if it passes the verifier
+    pub fn start(&mut self, code: ByteCode) -> Result<(), VerifyError> {
+        verify(&code)?;
         self.bytecode = code.bytecode;
         self.constants = code.constants;
-        self.pc = 0;
+        self.lines = code.lines;
+        self.reset();
+        Ok(())
+    }
+
+    /// appends code compiled after the current one, see `Compiler::compile_more`,
+    /// and moves to its start. Variables of the main program are kept
+    pub fn append(&mut self, code: ByteCode) {
+        // an error may have stopped the previous code inside a call
+        while !self.frames.is_empty() {
+            self.leave();
+        }
         self.stack.clear();
+
+        self.pc = self.bytecode.len();
+        self.bytecode.extend(code.bytecode);
+        self.constants.extend(code.constants);
+        self.lines.extend(code.lines);
     }
 
     pub fn reset(&mut self) {
         self.pc = 0;
+        self.fp = 0;
         self.stack.clear();
+        self.locals.clear();
+        self.frames.clear();
+    }
+
+    /// values waiting to be used by the next instructions
+    pub fn stack(&self) -> &[LinaValue] {
+        &self.stack
+    }
+
+    /// variables of the calls in progress, the main program ones first
+    pub fn locals(&self) -> &[Option<LinaValue>] {
+        &self.locals
+    }
+
+    /// the instruction that runs next, if the code has not ended,
+    /// the one after the prefix when it is wide
+    pub fn opcode(&self) -> Option<OpCode> {
+        let byte = *self.bytecode.get(self.pc)?;
+        match byte.try_into().ok()? {
+            OpCode::Wide => self.bytecode.get(self.pc + 1)?.to_owned().try_into().ok(),
+            opcode => Some(opcode),
+        }
     }
 
     fn push(&mut self, value: LinaValue) {
@@ -420,30 +643,106 @@ where
     }
 
     fn next_byte(&mut self) -> u8 {
-        assert!(self.pc < self.bytecode.len());
         self.pc += 1;
         self.bytecode[self.pc]
     }
 
-    fn next_addr(&mut self) -> usize {
-        let bytes = core::array::from_fn(|_| self.next_byte());
-        usize::from_ne_bytes(bytes)
+    /// the `N` bytes after the current one, read at once
+    fn next_bytes<const N: usize>(&mut self) -> [u8; N] {
+        let start = self.pc + 1;
+        self.pc += N;
+        self.bytecode[start..start + N]
+            .try_into()
+            .expect("the verifier checks that operands are complete")
     }
 
-    fn next_offs(&mut self) -> isize {
-        let bytes = core::array::from_fn(|_| self.next_byte());
-        isize::from_ne_bytes(bytes)
+    /// an operand of `size` bytes, or of `WIDE_SIZE` after a WIDE prefix
+    fn next_arg(&mut self, size: usize, wide: bool) -> usize {
+        match if wide { WIDE_SIZE } else { size } {
+            1 => u8::from_le_bytes(self.next_bytes()) as usize,
+            2 => u16::from_le_bytes(self.next_bytes()) as usize,
+            _ => u32::from_le_bytes(self.next_bytes()) as usize,
+        }
+    }
+
+    fn next_offs(&mut self, wide: bool) -> isize {
+        if wide {
+            i32::from_le_bytes(self.next_bytes()) as isize
+        } else {
+            i16::from_le_bytes(self.next_bytes()) as isize
+        }
+    }
+
+    /// makes room for `size` variables in the current frame
+    fn alloc(&mut self, size: usize) -> VmResult<()> {
+        let len = self.fp + size;
+        if len > self.locals.len() {
+            self.locals
+                .try_reserve(len - self.locals.len())
+                .map_err(|_| RuntimeError::SemMemoria)?;
+            self.locals.resize(len, None);
+        }
+        Ok(())
     }
 
-    fn store(&mut self, value: LinaValue, address: usize) {
-        if address >= self.stack.len() {
-            self.stack.resize_with(address + 1, Default::default);
+    fn store(&mut self, value: LinaValue, address: usize) -> VmResult<()> {
+        let slot = self
+            .locals
+            .get_mut(self.fp + address)
+            .ok_or_else(|| CodeError::from(format!("a variável {address} não foi alocada")))?;
+        *slot = Some(value);
+        Ok(())
+    }
+
+    fn load(&mut self, address: usize) -> VmResult<&LinaValue> {
+        // a variable has no value when its declaration failed in an earlier repl input
+        self.locals
+            .get(self.fp + address)
+            .and_then(Option::as_ref)
+            .ok_or(RuntimeError::SemValor)
+    }
+
+    fn call(&mut self, address: usize, argc: usize) -> VmResult<()> {
+        if self.frames.len() >= MAX_CALLS {
+            return Err(RuntimeError::EstouroDePilha);
         }
-        self.stack[address] = value;
+
+        // arguments leave the operand stack and become the first variables of the call
+        let sp = self.stack.len() - argc;
+        self.frames.push(Frame {
+            ret: self.pc,
+            fp: self.fp,
+            sp,
+        });
+        self.fp = self.locals.len();
+        self.locals.extend(self.stack.drain(sp..).map(Some));
+        self.pc = address - 1; // pc is incremented after each instruction
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        let frame = self.frames.pop().expect("return should be inside a call");
+        self.locals.truncate(self.fp);
+        self.stack.truncate(frame.sp);
+        self.fp = frame.fp;
+        self.pc = frame.ret;
     }
 
-    fn load(&mut self, address: usize) -> &LinaValue {
-        &self.stack[address]
+    /// attaches the position of the current statement to `err`
+    fn locate(&self, err: RuntimeError) -> VmError {
+        let i = self.lines.partition_point(|(start, _)| *start <= self.pc);
+        let pos = match i.checked_sub(1) {
+            Some(i) => self.lines[i].1.clone(),
+            None => TokenPos::default(),
+        };
+        VmError { pos, err }
+    }
+
+    fn index(idx: i64, len: usize) -> VmResult<usize> {
+        match usize::try_from(idx) {
+            Ok(i) if i < len => Ok(i),
+            _ => Err(RuntimeError::IndiceInvalido { idx, len }),
+        }
     }
 
     fn read(&mut self, stopc: &[u8]) -> Result<String, RuntimeError> {
@@ -463,13 +762,19 @@ where
     }
 
     pub fn run_instr(&mut self) -> VmResult<()> {
-        let opcode: OpCode = self.curr_byte().try_into()?;
+        let mut opcode: OpCode = self.curr_byte().try_into()?;
+        let wide = opcode == OpCode::Wide;
+        if wide {
+            self.pc += 1;
+            opcode = self.curr_byte().try_into()?;
+        }
 
         match opcode {
             OpCode::Halt => {}
+            OpCode::Wide => {} // the verifier refuses a prefix before another one
 
             OpCode::Const => {
-                let index = self.next_addr();
+                let index = self.next_arg(CONST_SIZE, wide);
                 let constant = &self.constants[index];
                 self.push(constant.clone());
             }
@@ -478,15 +783,27 @@ where
                 self.push(top.clone());
                 self.push(top);
             }
+            OpCode::Dup2 => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                self.push(lhs.clone());
+                self.push(rhs.clone());
+                self.push(lhs);
+                self.push(rhs);
+            }
             OpCode::Pop => _ = self.pop(),
 
             OpCode::CastI => {
                 let top = self.pop();
                 let val = match top {
-                    LinaValue::Float32(v) => v as i32,
-                    LinaValue::Int32(v) => v,
+                    // the fraction is dropped, but a value out of the range of inteiro is an error
+                    LinaValue::Float64(v) if v.is_finite() && v.trunc().abs() < i64::MAX as f64 => {
+                        v as i64
+                    }
+                    LinaValue::Float64(v) => Err(RuntimeError::Estouro(format!("inteiro({v})")))?,
+                    LinaValue::Int64(v) => v,
                     _ => {
-                        let msg = format!("não é possivel converter {top} em i32");
+                        let msg = format!("não é possivel converter {top} em i64");
                         Err(TypeError(msg))?
                     }
                 };
@@ -495,10 +812,10 @@ where
             OpCode::CastF => {
                 let top = self.pop();
                 let val = match top {
-                    LinaValue::Float32(v) => v,
-                    LinaValue::Int32(v) => v as f32,
+                    LinaValue::Float64(v) => v,
+                    LinaValue::Int64(v) => v as f64,
                     _ => {
-                        let msg = format!("não é possivel converter {top} em f32");
+                        let msg = format!("não é possivel converter {top} em f64");
                         Err(TypeError(msg))?
                     }
                 };
@@ -510,13 +827,60 @@ where
                 self.push(val.into());
             }
 
-            OpCode::Add => binop!(self, +, Int32, Float32, Address),
-            OpCode::Sub => binop!(self, -, Int32, Float32, Address),
-            OpCode::Mul => binop!(self, *, Int32, Float32, Address),
-            OpCode::Div => binop!(self, /, Int32, Float32, Address),
-            OpCode::Rem => binop!(self, %, Int32, Address),
-            OpCode::Or => binop!(self, |, Int32, Boolean, Address),
-            OpCode::And => binop!(self, &, Int32, Boolean, Address),
+            OpCode::Add => checked_binop!(self, +, checked_add, Float64),
+            OpCode::Sub => checked_binop!(self, -, checked_sub, Float64),
+            OpCode::Mul => checked_binop!(self, *, checked_mul, Float64),
+            OpCode::Div => checked_binop!(self, /, checked_div, Float64),
+            OpCode::Rem => checked_binop!(self, %, checked_rem),
+            OpCode::Pow => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+
+                // integers have no fractional results, so the exponent must not be negative
+                let result = match (&lhs, &rhs) {
+                    (LinaValue::Int64(base), LinaValue::Int64(exp)) => {
+                        if *exp < 0 {
+                            Err(RuntimeError::ExpoenteNegativo(*exp))?
+                        }
+                        u32::try_from(*exp)
+                            .ok()
+                            .and_then(|exp| base.checked_pow(exp))
+                            .ok_or_else(|| RuntimeError::Estouro(format!("{lhs} ^ {rhs}")))?
+                            .into()
+                    }
+                    (LinaValue::Float64(base), LinaValue::Float64(exp)) => base.powf(*exp).into(),
+                    _ => {
+                        let msg = format!(
+                            "tipos incompatíveis para a operação ^: lhs: {lhs:?}, rhs: {rhs:?}"
+                        );
+                        Err(TypeError(msg))?
+                    }
+                };
+
+                self.push(result);
+            }
+            OpCode::Or => binop!(self, |, Int64, Boolean, Address),
+            OpCode::And => binop!(self, &, Int64, Boolean, Address),
+            OpCode::Neg => {
+                let top = self.pop();
+                let val = match top {
+                    LinaValue::Int64(v) => v
+                        .checked_neg()
+                        .ok_or_else(|| RuntimeError::Estouro(format!("-({v})")))?
+                        .into(),
+                    LinaValue::Float64(v) => (-v).into(),
+                    _ => Err(TypeError(format!("não é possivel negar {top}")))?,
+                };
+                self.push(val);
+            }
+            OpCode::Not => {
+                let top = self.pop();
+                let val = match top {
+                    LinaValue::Boolean(v) => (!v).into(),
+                    _ => Err(TypeError(format!("não é possivel inverter {top}")))?,
+                };
+                self.push(val);
+            }
             OpCode::Eq => {
                 let rhs = self.pop();
                 let lhs = self.pop();
@@ -527,10 +891,10 @@ where
                 let lhs = self.pop();
                 self.push((lhs != rhs).into());
             }
-            OpCode::LT => binop!(self, <, Int32, Float32, Address),
-            OpCode::GT => binop!(self, >, Int32, Float32, Address),
-            OpCode::LE => binop!(self, <=, Int32, Float32, Address),
-            OpCode::GE => binop!(self, >=, Int32, Float32, Address),
+            OpCode::LT => binop!(self, <, Int64, Float64, Address),
+            OpCode::GT => binop!(self, >, Int64, Float64, Address),
+            OpCode::LE => binop!(self, <=, Int64, Float64, Address),
+            OpCode::GE => binop!(self, >=, Int64, Float64, Address),
 
             OpCode::Concat => {
                 let rhs = self.pop();
@@ -541,12 +905,12 @@ where
 
             // Controle de fluxo
             OpCode::Jmp => {
-                let offset = self.next_offs();
+                let offset = self.next_offs(wide);
                 self.pc = (self.pc as isize + offset) as usize;
             }
             OpCode::JmpT => {
                 let condition: bool = self.pop().try_into()?;
-                let offset = self.next_offs();
+                let offset = self.next_offs(wide);
 
                 if condition {
                     self.pc = (self.pc as isize + offset) as usize;
@@ -554,25 +918,81 @@ where
             }
             OpCode::JmpF => {
                 let condition: bool = self.pop().try_into()?;
-                let offset = self.next_offs();
+                let offset = self.next_offs(wide);
 
                 if !condition {
                     self.pc = (self.pc as isize + offset) as usize;
                 }
             }
 
-            OpCode::Call => todo!(),
-            OpCode::Return => todo!(),
+            OpCode::Call => {
+                let address = self.next_arg(ADDR_SIZE, wide);
+                let argc = self.next_arg(SLOT_SIZE, wide);
+                self.call(address, argc)?;
+            }
+            OpCode::Return => {
+                let value = self.pop();
+                self.leave();
+                self.push(value);
+            }
+            OpCode::ReturnV => self.leave(),
 
+            OpCode::Alloc => {
+                let size = self.next_arg(ADDR_SIZE, wide);
+                self.alloc(size)?;
+            }
             OpCode::Load => {
-                let address = self.next_addr();
-                let value = self.load(address).clone();
+                let address = self.next_arg(SLOT_SIZE, wide);
+                let value = self.load(address)?.clone();
                 self.push(value);
             }
             OpCode::Store => {
                 let value = self.pop();
-                let address = self.next_addr();
-                self.store(value, address);
+                let address = self.next_arg(SLOT_SIZE, wide);
+                self.store(value, address)?;
+            }
+
+            OpCode::List => {
+                let len = self.next_arg(SLOT_SIZE, wide);
+                let items = self.stack.split_off(self.stack.len() - len);
+                self.push(items.into());
+            }
+            OpCode::Index => {
+                let idx: i64 = self.pop().try_into()?;
+                let list: LinaList = self.pop().try_into()?;
+                let list = list.borrow();
+                let value = list[Self::index(idx, list.len())?].clone();
+                self.push(value);
+            }
+            OpCode::SetIndex => {
+                let value = self.pop();
+                let idx: i64 = self.pop().try_into()?;
+                let list: LinaList = self.pop().try_into()?;
+                let mut list = list.borrow_mut();
+                let i = Self::index(idx, list.len())?;
+                list[i] = value.clone();
+                self.push(value);
+            }
+            OpCode::Len => {
+                let len = match self.pop() {
+                    LinaValue::List(list) => list.borrow().len(),
+                    LinaValue::String(text) => text.chars().count(),
+                    value => Err(TypeError(format!("{value} não possui tamanho")))?,
+                };
+                self.push(LinaValue::Int64(len as i64)); // a len always fits in i64
+            }
+            OpCode::Append => {
+                let value = self.pop();
+                let list: LinaList = self.pop().try_into()?;
+                list.borrow_mut().push(value);
+            }
+            OpCode::Remove => {
+                let idx: i64 = self.pop().try_into()?;
+                let list: LinaList = self.pop().try_into()?;
+                let mut list = list.borrow_mut();
+                let i = Self::index(idx, list.len())?;
+                let value = list.remove(i);
+                self.push(value);
             }
 
             OpCode::Write => {
@@ -580,40 +1000,43 @@ where
                 write!(self.stdout, "{value}")?;
             }
             OpCode::ReadL => {
-                let line = self.read(&[b'\n'])?;
+                let line = self.read(b"\n")?;
                 self.push(LinaValue::String(line));
             }
             OpCode::ReadI => {
-                let value = self.read(&[b'\n', b' '])?.parse::<i32>()?;
-                self.push(LinaValue::Int32(value));
+                let value = self.read(b"\n ")?.trim().parse::<i64>()?;
+                self.push(LinaValue::Int64(value));
             }
             OpCode::ReadF => {
-                let value = self.read(&[b'\n', b' '])?.parse::<f32>()?;
-                self.push(LinaValue::Float32(value));
+                let value = self.read(b"\n ")?.trim().parse::<f64>()?;
+                self.push(LinaValue::Float64(value));
             }
         };
 
         Ok(())
     }
 
-    pub fn run(&mut self) -> VmResult<()> {
+    pub fn run(&mut self) -> Result<(), VmError> {
         loop {
             if self.curr_byte() == 0b0 {
                 break Ok(());
             } else {
-                self.run_instr()?;
-                self.next_byte();
+                self.run_instr().map_err(|err| self.locate(err))?;
+                self.pc += 1;
             }
         }
     }
 
-    pub fn run_single(&mut self) -> VmResult<VmState> {
+    pub fn run_single(&mut self) -> Result<VmState, VmError> {
         if self.curr_byte() == 0b0 {
             return Ok(VmState::Idle);
         }
 
-        self.run_instr()?;
-        let next: OpCode = self.next_byte().try_into()?;
+        self.run_instr().map_err(|err| self.locate(err))?;
+        let next: OpCode = self
+            .next_byte()
+            .try_into()
+            .map_err(|err: CodeError| self.locate(err.into()))?;
 
         let state = match next {
             OpCode::Write => VmState::WillWrite,
@@ -625,52 +1048,71 @@ where
     }
 
     pub fn decompile(&mut self) -> VmResult<()> {
-        loop {
-            let opcode: OpCode = self.curr_byte().try_into()?;
+        // functions are placed after the main program, so go until the end of the bytecode
+        while self.pc < self.bytecode.len() {
+            let start = self.pc;
+            let mut opcode: OpCode = self.curr_byte().try_into()?;
+            let wide = opcode == OpCode::Wide;
+            if wide {
+                self.pc += 1;
+                opcode = self.curr_byte().try_into()?;
+            }
+            let prefix = if wide { "WIDE " } else { "" };
+            write!(self.stdout, "{start:#06x}\t{prefix}")?;
 
             match opcode {
-                OpCode::Halt => {
-                    writeln!(self.stdout, "{opcode}")?;
-                    return Ok(());
-                }
                 OpCode::Const => {
-                    let index = self.next_addr();
+                    let index = self.next_arg(CONST_SIZE, wide);
                     let value = &self.constants[index];
                     let fmt_value = match value {
-                        LinaValue::Int32(value) => format!("{}i32", value),
-                        LinaValue::Float32(value) => format!("{}f32", value),
+                        LinaValue::Int64(value) => format!("{}i64", value),
+                        LinaValue::Float64(value) => format!("{}f64", value),
                         LinaValue::String(value) => format!("\"{}\"", value.escape_default()),
                         LinaValue::Boolean(value) => format!("{}", value),
                         LinaValue::Address(value) => format!("{:#02x}", value),
+                        LinaValue::List(..) => format!("{}", value),
                     };
                     writeln!(self.stdout, "{opcode}\t{index:#02x}\t{fmt_value}")?;
                 }
                 OpCode::Jmp => {
-                    let index = self.next_offs();
+                    let index = self.next_offs(wide);
                     writeln!(self.stdout, "{opcode}\t{index}")?;
                 }
                 OpCode::JmpT => {
-                    let index = self.next_offs();
+                    let index = self.next_offs(wide);
                     writeln!(self.stdout, "{opcode}\t{index}")?;
                 }
                 OpCode::JmpF => {
-                    let index = self.next_offs();
+                    let index = self.next_offs(wide);
                     writeln!(self.stdout, "{opcode}\t{index}")?;
                 }
+                OpCode::Alloc => {
+                    let size = self.next_arg(ADDR_SIZE, wide);
+                    writeln!(self.stdout, "{opcode}\t{size}")?;
+                }
                 OpCode::Load => {
-                    let index = self.next_addr();
+                    let index = self.next_arg(SLOT_SIZE, wide);
                     writeln!(self.stdout, "{opcode}\t{index:#02x}")?;
                 }
                 OpCode::Store => {
-                    let index = self.next_addr();
+                    let index = self.next_arg(SLOT_SIZE, wide);
                     writeln!(self.stdout, "{opcode}\t{index:#02x}")?;
                 }
-                OpCode::Call => todo!(),
-                OpCode::Return => todo!(),
+                OpCode::List => {
+                    let len = self.next_arg(SLOT_SIZE, wide);
+                    writeln!(self.stdout, "{opcode}\t{len}")?;
+                }
+                OpCode::Call => {
+                    let address = self.next_arg(ADDR_SIZE, wide);
+                    let argc = self.next_arg(SLOT_SIZE, wide);
+                    writeln!(self.stdout, "{opcode}\t{address:#06x}\t{argc}")?;
+                }
                 _ => writeln!(self.stdout, "{opcode}")?,
             };
 
-            self.next_byte();
+            self.pc += 1;
         }
+
+        Ok(())
     }
 }
@@ -0,0 +1,58 @@
+//! Helpers shared by the integration tests and the benchmark, which include
+//! this file with `mod common;`. Not every one of them uses every helper.
+#![allow(dead_code)]
+
+use lina::compiler::{compile, ByteCode};
+use lina::lexer::lex;
+use lina::parser::parse;
+use lina::syntax::Program;
+use lina::vm::LinaVm;
+
+/// a program with functions, recursion, loops, lists, texts and reals
+pub const PROGRAMA: &str = r#"
+    programa Teste
+
+    função fib(inteiro n): inteiro
+        se n < 2 entao retorne n fim
+        retorne fib(n - 1) + fib(n - 2)
+    fim
+
+    função mostrar(texto nome, inteiro valor)
+        saida(nome, " = ", valor)
+    fim
+
+    função saudar(texto nome): texto
+        retorne "olá, {nome}!"
+    fim
+
+    lista de inteiro l := [0]
+    para i := 0 ate 10 repetir
+        enquanto tamanho(l) < i repetir
+            adicionar(l, fib(i))
+        fim
+    fim
+    mostrar("l", remover(l, 1) + tamanho(l))
+
+    real r := 2.5
+    booleano b := verdadeiro
+    lista de inteiro m := [1, -2, 9_000_000_000]
+    saida(saudar("mundo"), " ", r * 2, " ", b, " ", m)
+"#;
+
+/// parses `code`, which must have no errors
+pub fn program(code: &str) -> Program<'_> {
+    let tokens = lex(code).unwrap_or_else(|err| panic!("{}", err.msg));
+    parse(tokens).unwrap_or_else(|err| panic!("{}", err.errors[0].msg))
+}
+
+/// compiles `code`, which must have no errors
+pub fn build(code: &str) -> ByteCode {
+    compile(&program(code)).unwrap_or_else(|err| panic!("{}", err.msg))
+}
+
+/// what `bytecode` prints when run
+pub fn run(bytecode: ByteCode) -> String {
+    let mut vm = LinaVm::new(bytecode, &[][..], Vec::new()).expect("code should be valid");
+    vm.run().unwrap_or_else(|err| panic!("{}", err.err));
+    String::from_utf8(vm.stdout).unwrap()
+}
@@ -0,0 +1,152 @@
+//! `formatar` rewrites the files of its users, so its output must mean the same
+//! program as the input and be left as is when formatted again.
+
+mod common;
+
+use std::fs;
+
+use lina::formatter::format;
+use lina::lexer::lex_with_comments;
+use lina::parser::parse;
+use lina::vm::LinaVm;
+
+/// comments and blank lines where the formatter has to decide where they go
+const COMENTARIOS: &str = r#"# antes do programa
+
+/* um bloco
+   de várias linhas */
+programa   Comentarios # depois do nome
+
+
+inteiro /* no meio */ x := 1 + # dentro da expressão
+    2
+lista de inteiro l := [1,
+    # entre os itens
+    2, 3]
+
+função dobro(inteiro n): inteiro # na assinatura
+    # antes do retorno
+    retorne n * 2
+    # depois do retorno
+fim
+
+se x > 2 entao saida(x) fim # numa linha só
+se x > 2 entao
+    # bloco só com comentário
+senao se x = 3 entao saida("três") # no senao se
+senao
+    saida(dobro(x)) /* fim da linha */
+fim
+
+para i := 0 ate 2 repetir # no para
+    enquanto falso repetir pare fim
+fim
+# no fim do arquivo
+"#;
+
+fn formatted(name: &str, code: &str) -> String {
+    let (tokens, comments) =
+        lex_with_comments(code).unwrap_or_else(|err| panic!("{name}: {}", err.msg));
+    let program = parse(tokens).unwrap_or_else(|err| panic!("{name}: {}", err.errors[0].msg));
+    format(&program, code, comments)
+}
+
+/// the `desmontar` output of `code`
+fn disassembly(name: &str, code: &str) -> String {
+    let mut vm =
+        LinaVm::new(common::build(code), &[][..], Vec::new()).expect("code should be valid");
+    vm.decompile().unwrap_or_else(|err| panic!("{name}: {err}"));
+    String::from_utf8(vm.stdout).unwrap()
+}
+
+fn check(name: &str, code: &str) {
+    let once = formatted(name, code);
+    assert_eq!(
+        disassembly(name, &once),
+        disassembly(name, code),
+        "{name}: the formatted program is another one:\n{once}"
+    );
+    assert_eq!(
+        formatted(name, &once),
+        once,
+        "{name}: formatting again changes the file"
+    );
+}
+
+#[test]
+fn examples_keep_their_program() {
+    let mut files = fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../examples"))
+        .expect("examples should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect::<Vec<_>>();
+    files.sort();
+    assert!(!files.is_empty());
+
+    for path in files {
+        let code = fs::read_to_string(&path).unwrap();
+        check(&path.display().to_string(), &code);
+    }
+}
+
+#[test]
+fn comments_in_odd_places() {
+    check("comentarios", COMENTARIOS);
+
+    // every comment is still there
+    let once = formatted("comentarios", COMENTARIOS);
+    let (_, comments) = lex_with_comments(COMENTARIOS).unwrap();
+    for comment in comments {
+        assert!(once.contains(comment.text), "lost {}", comment.text);
+    }
+}
+
+#[test]
+fn texts_are_kept_as_written() {
+    let code = r#"programa Textos
+
+texto poema := "uma linha
+    e outra,\tcom \u{41} escapes\n"
+inteiro n := 2
+saida("n = {n}
+e o dobro {n * 2}\n")
+"#;
+    check("textos", code);
+    assert_eq!(formatted("textos", code), code);
+}
+
+#[test]
+fn numbers_are_kept_as_written() {
+    let code = r#"programa Numeros
+
+inteiro mascara := 0xFF
+inteiro bits := 0b1010
+inteiro milhao := 1_000_000
+real mil := 2e3
+inteiro zero
+para i := 0x0 ate 0b11 incremento 1_0 repetir
+    saida(i + mascara)
+fim
+"#;
+    check("numeros", code);
+    assert_eq!(formatted("numeros", code), code);
+}
+
+#[test]
+fn comments_stay_out_of_interpolations() {
+    for code in [
+        "programa Teste\ninteiro a := 1\nsaida(\"v={a # c}\")\n",
+        "programa Teste\ninteiro a := 1\nsaida(\"w={a /* c */ + 1}\")\n",
+    ] {
+        let err = lex_with_comments(code).expect_err("a comment inside { } is refused");
+        assert_eq!((err.row, err.col), (3, 12), "{}", err.msg);
+    }
+
+    let code = "programa Teste
+
+inteiro a := 1
+saida(\"w={a + 1} # /* no texto */\") /* depois */ # e aqui
+";
+    check("interpolacao", code);
+    assert_eq!(formatted("interpolacao", code), code);
+}
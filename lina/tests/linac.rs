@@ -0,0 +1,56 @@
+mod common;
+
+use common::{build, run, PROGRAMA};
+use lina::linac::{decode, encode, MAGIC};
+
+#[test]
+fn round_trip() {
+    let bytecode = build(PROGRAMA);
+    let expected = run(build(PROGRAMA));
+
+    let file = encode(&bytecode, true);
+    assert!(file.starts_with(&MAGIC));
+
+    let decoded = decode(&file).expect("file should be valid");
+    assert_eq!(decoded.bytecode, bytecode.bytecode);
+    assert_eq!(decoded.constants, bytecode.constants);
+    assert_eq!(decoded.lines, bytecode.lines);
+    assert_eq!(run(decoded), expected);
+}
+
+#[test]
+fn debug_info_is_optional() {
+    let bytecode = build(PROGRAMA);
+    let with = encode(&bytecode, true);
+    let without = encode(&bytecode, false);
+    assert!(without.len() < with.len());
+
+    let decoded = decode(&without).expect("file should be valid");
+    assert!(decoded.lines.is_empty());
+    assert_eq!(run(decoded), run(bytecode));
+}
+
+#[test]
+fn rejects_broken_files() {
+    let file = encode(&build(PROGRAMA), true);
+
+    let mut version = file.clone();
+    version[4] = 99;
+    let truncated = &file[..file.len() - 1];
+    let mut extra = file.clone();
+    extra.push(7);
+
+    let errors = [
+        decode(b"programa Teste").unwrap_err(),
+        decode(&version).unwrap_err(),
+        decode(truncated).unwrap_err(),
+        decode(&extra).unwrap_err(),
+        decode(&file[..6]).unwrap_err(),
+    ];
+    let msgs = errors.map(|err| err.msg);
+    assert_eq!(msgs[0], "o arquivo não é um programa compilado da lina");
+    assert!(msgs[1].starts_with("versão 99"));
+    assert_eq!(msgs[2], "a seção passa do fim do arquivo");
+    assert_eq!(msgs[3], "o arquivo termina antes do esperado");
+    assert_eq!(msgs[4], "falta a seção de constantes");
+}
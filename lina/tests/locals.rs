@@ -0,0 +1,286 @@
+//! Runs programs one instruction at a time and checks, after each one, that the
+//! variables and the operand stack of the vm only change in the way the instruction
+//! allows, so a value on the stack never turns into a variable or the other way round.
+
+mod common;
+
+use lina::repl::Repl;
+use lina::vm::{LinaValue, LinaVm, OpCode, RuntimeError};
+
+type Vm = LinaVm<&'static [u8], Vec<u8>>;
+
+fn build(code: &str) -> Vm {
+    LinaVm::new(common::build(code), &[][..], Vec::new()).expect("compiled code should be valid")
+}
+
+/// checks the effect of `opcode` from the state before it to the one after it
+fn verify(
+    opcode: OpCode,
+    (stack, locals): (&[LinaValue], &[Option<LinaValue>]),
+    (new_stack, new_locals): (&[LinaValue], &[Option<LinaValue>]),
+) {
+    match opcode {
+        OpCode::Alloc => {
+            assert_eq!(new_stack, stack, "alloc changed the stack");
+            assert!(new_locals.starts_with(locals), "alloc changed a variable");
+            assert!(new_locals[locals.len()..].iter().all(Option::is_none));
+        }
+        OpCode::Store => {
+            let (value, rest) = stack.split_last().expect("store without a value");
+            assert_eq!(new_stack, rest, "store changed more than its value");
+            assert_eq!(new_locals.len(), locals.len(), "store allocated a variable");
+
+            let changed = locals
+                .iter()
+                .zip(new_locals)
+                .filter(|(old, new)| old != new)
+                .collect::<Vec<_>>();
+            assert!(
+                changed.len() <= 1,
+                "store changed {} variables",
+                changed.len()
+            );
+            if let Some((_, new)) = changed.first() {
+                assert_eq!(new.as_ref(), Some(value));
+            }
+        }
+        OpCode::Load => {
+            assert_eq!(new_locals, locals, "load changed a variable");
+            let (value, rest) = new_stack.split_last().expect("load without a value");
+            assert_eq!(rest, stack, "load changed the stack below its value");
+            assert!(locals.contains(&Some(value.clone())));
+        }
+        OpCode::Call => {
+            let args = &stack[new_stack.len()..];
+            assert_eq!(new_stack, &stack[..new_stack.len()]);
+            assert!(new_locals.starts_with(locals), "call changed a variable");
+
+            let params = &new_locals[locals.len()..];
+            assert_eq!(params.len(), args.len(), "arguments not moved to the call");
+            assert!(params.iter().zip(args).all(|(p, a)| p.as_ref() == Some(a)));
+        }
+        OpCode::Return => {
+            let (value, rest) = new_stack.split_last().expect("return without a value");
+            assert_eq!(Some(value), stack.last());
+            assert!(stack.starts_with(rest), "return changed the caller stack");
+            assert!(locals.starts_with(new_locals), "return changed a variable");
+        }
+        OpCode::ReturnV => {
+            assert!(
+                stack.starts_with(new_stack),
+                "return changed the caller stack"
+            );
+            assert!(locals.starts_with(new_locals), "return changed a variable");
+        }
+        _ => assert_eq!(new_locals, locals, "{opcode} changed a variable"),
+    }
+}
+
+/// runs `code` checking every instruction, returning what it printed
+fn run(code: &str) -> String {
+    let mut vm = build(code);
+
+    loop {
+        let opcode = vm.opcode().expect("code ended without halt");
+        if opcode == OpCode::Halt {
+            break;
+        }
+
+        let (stack, locals) = (vm.stack().to_vec(), vm.locals().to_vec());
+        vm.run_single().unwrap_or_else(|err| panic!("{}", err.err));
+        verify(opcode, (&stack, &locals), (vm.stack(), vm.locals()));
+    }
+
+    assert!(vm.stack().is_empty(), "values left on the stack");
+    String::from_utf8(vm.stdout).unwrap()
+}
+
+#[test]
+fn call_keeps_pending_operands() {
+    let out = run(r#"
+        programa Teste
+
+        função dobro(inteiro n): inteiro
+            inteiro a := n
+            se verdadeiro entao
+                inteiro b := a
+                inteiro c := b + a
+                retorne c
+            fim
+            retorne 0
+        fim
+
+        inteiro x := 1
+        saida(x + 10 * dobro(2) + dobro(dobro(3)))
+    "#);
+    assert_eq!(out, "53\n");
+}
+
+#[test]
+fn recursion_keeps_each_frame() {
+    let out = run(r#"
+        programa Teste
+
+        função fib(inteiro n): inteiro
+            se n < 2 entao retorne n fim
+            inteiro a := fib(n - 1)
+            inteiro b := fib(n - 2)
+            retorne a + b
+        fim
+
+        saida(fib(15))
+    "#);
+    assert_eq!(out, "610\n");
+}
+
+#[test]
+fn reused_slots_start_from_their_declaration() {
+    let out = run(r#"
+        programa Teste
+
+        inteiro x := 1
+        se verdadeiro entao
+            inteiro a := 2
+            inteiro b := 3
+            saida(x + a + b)
+        fim
+        se verdadeiro entao
+            inteiro c := 4
+            saida(x * 100 + c)
+        fim
+        inteiro y := 5
+        saida(x + y)
+    "#);
+    assert_eq!(out, "6\n104\n6\n");
+}
+
+#[test]
+fn declarations_inside_loops() {
+    let out = run(r#"
+        programa Teste
+
+        inteiro total := 0
+        para i := 1 ate 4 repetir
+            inteiro quadrado := i * i
+            enquanto quadrado > 0 repetir
+                inteiro parte := quadrado % 10
+                total += parte
+                quadrado := quadrado / 10
+            fim
+        fim
+        saida(total)
+    "#);
+    assert_eq!(out, "21\n");
+}
+
+#[test]
+fn calls_inside_lists() {
+    let out = run(r#"
+        programa Teste
+
+        função tres(inteiro n): lista de inteiro
+            inteiro a := n + 1
+            retorne [n, a, a + 1]
+        fim
+
+        lista de inteiro l := [0, tres(1)[2], tamanho(tres(5)), tres(7)[0]]
+        saida(l)
+    "#);
+    assert_eq!(out, "[0, 3, 3, 7]\n");
+}
+
+#[test]
+fn procedure_returns_inside_loop() {
+    let out = run(r#"
+        programa Teste
+
+        função mostrar(inteiro limite)
+            para i := 0 ate 10 repetir
+                inteiro dobro := i * 2
+                se dobro > limite entao retorne fim
+                saida(dobro)
+            fim
+        fim
+
+        inteiro antes := 7
+        mostrar(4)
+        saida(antes)
+    "#);
+    assert_eq!(out, "0\n2\n4\n7\n");
+}
+
+#[test]
+fn frame_holds_most_variables_alive() {
+    let mut vm = build(
+        r#"
+        programa Teste
+
+        inteiro x := 1
+        se verdadeiro entao
+            inteiro a := 2
+            inteiro b := 3
+        fim
+        se verdadeiro entao
+            inteiro c := 4
+        fim
+    "#,
+    );
+    vm.run().unwrap_or_else(|err| panic!("{}", err.err));
+
+    assert_eq!(vm.locals()[0], Some(LinaValue::Int64(1)));
+    assert_eq!(vm.locals().len(), 3, "slots of ended blocks are reused");
+    assert!(vm.stack().is_empty());
+}
+
+#[test]
+fn repl_keeps_variables_after_error() {
+    let mut repl = Repl::new(&[][..], Vec::new());
+
+    let inputs = [
+        "seja a := 1",
+        "função f(inteiro n): inteiro\n inteiro b := 10 / n\n retorne b\nfim",
+        "seja c := a + f(0)",
+        "seja d := [a, f(5)]",
+        "saida(a, d)",
+    ];
+    let results = inputs.map(|input| repl.eval(input.into()).is_ok());
+    assert_eq!(results, [true, true, false, true, true]);
+
+    assert_eq!(String::from_utf8_lossy(&repl.vm.stdout), "1[1, 2]\n");
+    assert!(repl.vm.stack().is_empty());
+    assert_eq!(repl.vm.locals().len(), 3);
+}
+
+#[test]
+fn wide_slots_and_long_jumps() {
+    // more variables than a narrow slot holds, inside a block too long for a narrow jump
+    let mut code = String::from("programa Teste\ninteiro total := 0\nse verdadeiro entao\n");
+    for i in 0..300 {
+        code += &format!("inteiro v{i} := {i}\n");
+    }
+    for i in 0..6000 {
+        code += &format!("total += v{}\n", i % 300);
+    }
+    code += "fim\nsaida(total)\n";
+
+    let code: &'static str = code.leak();
+    assert_eq!(run(code), format!("{}\n", (0..300).sum::<i64>() * 20));
+}
+
+#[test]
+fn recursion_without_end_is_an_error() {
+    let mut vm = build(
+        r#"
+        programa Teste
+
+        função f(inteiro n): inteiro
+            retorne f(n + 1)
+        fim
+
+        saida(f(0))
+    "#,
+    );
+    let err = vm.run().expect_err("recursion should stop");
+    assert!(matches!(err.err, RuntimeError::EstouroDePilha));
+    assert_eq!(err.pos.row, 5);
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::program;
+use lina::compiler::compile;
+use lina::optimizer::optimize;
+use lina::syntax::{Expression, Program, SyntaxTree};
+use lina::token::Literal;
+use lina::vm::{LinaVm, OpCode};
+
+fn optimized(code: &str) -> Program<'_> {
+    let mut program = program(code);
+    optimize(&mut program).unwrap_or_else(|err| panic!("{}", err.msg));
+    program
+}
+
+/// what `program` prints and the number of instructions run to print it
+fn run(program: &Program) -> (String, usize) {
+    let bytecode = compile(program).unwrap_or_else(|err| panic!("{}", err.msg));
+    let mut vm = LinaVm::new(bytecode, &[][..], Vec::new()).expect("code should be valid");
+
+    let mut steps = 0;
+    while vm.opcode() != Some(OpCode::Halt) {
+        vm.run_single().unwrap_or_else(|err| panic!("{}", err.err));
+        steps += 1;
+    }
+    (String::from_utf8(vm.stdout).unwrap(), steps)
+}
+
+/// runs `code` with and without the optimizer, which must print the same
+/// running fewer instructions, returning what it printed
+fn compare(code: &str) -> String {
+    let (out, steps) = run(&program(code));
+    let (optimized_out, optimized_steps) = run(&optimized(code));
+    assert_eq!(optimized_out, out);
+    assert!(
+        optimized_steps < steps,
+        "{optimized_steps} >= {steps} steps"
+    );
+    out
+}
+
+#[test]
+fn folds_operations_and_casts() {
+    let code = r#"
+        programa Teste
+        real a := 0.0 - 1.0
+        real b := 2 * 3 + 1.0
+        texto c := "x = " + (10 % 4) ^ 3
+        booleano d := nao (1 < 2 e 2.5 >= 2)
+    "#;
+    let program = optimized(code);
+
+    let values = program
+        .block
+        .iter_stmts()
+        .map(|stmt| match stmt {
+            SyntaxTree::Assign {
+                exp: Expression::Literal(literal, _),
+                ..
+            } => literal.clone(),
+            stmt => panic!("not folded: {stmt}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [
+            Literal::Decimal(-1.0),
+            Literal::Decimal(7.0),
+            Literal::Texto("x = 8".into()),
+            Literal::Booleano(false),
+        ]
+    );
+}
+
+#[test]
+fn keeps_operations_on_variables() {
+    let out = compare(
+        r#"
+        programa Teste
+        inteiro n := 3
+        real x := n / 2 + 1.5 * 2
+        saida(x, " ", n * (4 - 1), " ", -n + -(2 ^ 3))
+    "#,
+    );
+    assert_eq!(out, "4 9 -11\n");
+}
+
+#[test]
+fn removes_branches_never_taken() {
+    let code = r#"
+        programa Teste
+        se 1 > 2 entao
+            saida("a")
+        senao se 2 > 1 entao
+            saida("b")
+        senao
+            saida("c")
+        fim
+        se falso entao
+            saida("d")
+        fim
+        enquanto 1 = 2 repetir
+            saida("e")
+        fim
+        saida("f")
+    "#;
+    assert_eq!(compare(code), "b\nf\n");
+
+    // what is left runs without any test
+    let program = optimized(code);
+    let stmts = program.block.iter_stmts().collect::<Vec<_>>();
+    assert_eq!(stmts.len(), 2);
+    assert!(stmts
+        .iter()
+        .all(|stmt| matches!(stmt, SyntaxTree::Expr { .. })));
+}
+
+#[test]
+fn keeps_scope_of_taken_branch() {
+    let out = compare(
+        r#"
+        programa Teste
+
+        função escolha(inteiro n): inteiro
+            se falso entao
+                retorne 0
+            senao
+                inteiro dobro := n * 2
+                retorne dobro
+            fim
+        fim
+
+        inteiro dobro := 1
+        se verdadeiro entao
+            inteiro dobro := 10
+            saida(dobro)
+        fim
+        enquanto dobro < 10 repetir
+            se 0 > 1 entao
+                pare
+            senao
+                dobro := escolha(dobro)
+            fim
+        fim
+        saida(dobro)
+    "#,
+    );
+    assert_eq!(out, "10\n16\n");
+}
+
+#[test]
+fn reports_folding_errors() {
+    let code = r#"
+        programa Teste
+        inteiro x := 1
+        saida(x)
+        saida(x + 10 / (5 - 5))
+    "#;
+    let mut program = program(code);
+    let err = optimize(&mut program).unwrap_err();
+    assert_eq!(err.pos.row, 5);
+    assert_eq!(err.msg, "divisão por zero em 10 / 0");
+
+    let mut program = common::program("programa Teste\nsaida(9223372036854775807 + 1)");
+    let err = optimize(&mut program).unwrap_err();
+    assert!(err.msg.contains("não cabe em um inteiro"), "{}", err.msg);
+}
+
+#[test]
+fn ignores_errors_of_removed_code() {
+    let out = compare(
+        r#"
+        programa Teste
+        se falso entao
+            saida(1 / 0)
+        fim
+        saida(1 + 1)
+    "#,
+    );
+    assert_eq!(out, "2\n");
+}
+
+#[test]
+fn keeps_the_sign_of_zero() {
+    let out = compare(
+        r#"
+        programa Teste
+        real a := 0.0
+        real w := -0.0
+        saida(1.0 / w, " ", 1.0 / a, " ", 2 * 3)
+    "#,
+    );
+    assert_eq!(out, "-inf inf 6\n");
+}
@@ -0,0 +1,128 @@
+use lina::lexer::lex;
+use lina::parser::parse;
+
+/// the messages of the syntax errors of `code`, in order
+fn errors(code: &str) -> Vec<String> {
+    let tokens = lex(code).unwrap_or_else(|err| panic!("{}", err.msg));
+    match parse(tokens) {
+        Ok(_) => Vec::new(),
+        Err(err) => err.errors.into_iter().map(|err| err.msg).collect(),
+    }
+}
+
+#[test]
+fn reports_every_error() {
+    let errs = errors(
+        r#"
+        programa Teste
+        inteiro x := "um"
+        saida(y)
+        se x entao
+            pare
+        fim
+        saida(x)
+    "#,
+    );
+    assert_eq!(errs.len(), 4, "{errs:?}");
+}
+
+#[test]
+fn skipped_loop_body_is_inside_a_loop() {
+    let errs = errors(
+        r#"
+        programa Teste
+        inteiro x := 1
+        para i := 1 ate 3 incremento x repetir
+            se i = 2 entao pare fim
+        fim
+        enquanto x + repetir
+            continue
+        fim
+    "#,
+    );
+    assert_eq!(errs.len(), 2, "{errs:?}");
+
+    // outside a loop it is still an error
+    let errs = errors("programa Teste\nse 1 + entao\npare\nfim\n");
+    assert_eq!(errs.len(), 2, "{errs:?}");
+    assert_eq!(errs[1], "pare só pode ser usado dentro de um laço");
+}
+
+#[test]
+fn skipped_function_body_is_inside_a_function() {
+    let errs = errors(
+        r#"
+        programa Teste
+        função f(inteiro a, inteiro b: inteiro
+            inteiro c := a + b
+            retorne c
+        fim
+        saida(c)
+    "#,
+    );
+    assert_eq!(
+        errs,
+        [
+            "esperado vírgula ou parênteses de fechamento, encontrou delimitador: :",
+            "variavel não definida c",
+        ]
+    );
+}
+
+#[test]
+fn no_missing_return_after_errors_in_the_body() {
+    let errs = errors(
+        r#"
+        programa Teste
+        função f(inteiro a): inteiro
+            retorne "a"
+        fim
+        função g(inteiro a): inteiro
+            saida(a)
+        fim
+    "#,
+    );
+    assert_eq!(
+        errs,
+        [
+            "tipos incompatíveis inteiro e texto",
+            "a função g deve retornar um valor do tipo inteiro",
+        ]
+    );
+}
+
+#[test]
+fn functions_do_not_see_variables_of_the_program() {
+    let code = r#"
+        programa Teste
+        inteiro total := 0
+        função some(inteiro n): inteiro
+            retorne total + n
+        fim
+        saida(y)
+    "#;
+    let tokens = lex(code).unwrap();
+    let Err(err) = parse(tokens) else {
+        panic!("code should be refused");
+    };
+    let errs = err.errors;
+    assert_eq!(errs.len(), 2);
+
+    let hint = errs[0].hint.as_deref().unwrap();
+    assert!(hint.starts_with("funções só enxergam"), "{hint}");
+    assert_eq!(errs[0].labels[0].pos.row, 3);
+
+    // outside a function, the variable is just missing
+    let hint = errs[1].hint.as_deref().unwrap();
+    assert!(hint.starts_with("declare a variável"), "{hint}");
+    assert!(errs[1].labels.is_empty());
+}
+
+#[test]
+fn nao_is_only_for_booleans() {
+    assert!(errors("programa Teste\nsaida(nao (1 > 2))").is_empty());
+    assert_eq!(
+        errors("programa Teste\nsaida(nao 0)"),
+        ["operação nao não suportada para inteiro"]
+    );
+}
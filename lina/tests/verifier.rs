@@ -0,0 +1,313 @@
+mod common;
+
+use common::{build, PROGRAMA};
+use lina::compiler::ByteCode;
+use lina::verifier::{verify, InvalidCode, VerifyError};
+use lina::vm::{LinaValue, LinaVm, OpCode, ADDR_SIZE};
+
+/// assembles opcodes and their operands in the size each one has,
+/// written as i64 so jumps can go back
+fn asm(code: &[(OpCode, &[i64])]) -> ByteCode {
+    let mut bytecode = Vec::new();
+    for (op, args) in code {
+        bytecode.push(*op as u8);
+        for (arg, &size) in args.iter().zip(op.operands()) {
+            bytecode.extend(&arg.to_le_bytes()[..size]);
+        }
+    }
+
+    ByteCode {
+        bytecode,
+        constants: vec![LinaValue::Int64(1), LinaValue::Boolean(true)],
+        lines: Vec::new(),
+    }
+}
+
+fn fails(code: &[(OpCode, &[i64])]) -> VerifyError {
+    verify(&asm(code)).expect_err("code should be refused")
+}
+
+#[test]
+fn compiled_code_is_valid() {
+    assert_eq!(verify(&build(PROGRAMA)), Ok(()));
+}
+
+#[test]
+fn refuses_unknown_opcode() {
+    let mut code = asm(&[(OpCode::Halt, &[])]);
+    code.bytecode.insert(0, 0xEE);
+
+    let err = verify(&code).unwrap_err();
+    assert_eq!(
+        err,
+        VerifyError {
+            offset: 0,
+            err: InvalidCode::Opcode(0xEE)
+        }
+    );
+}
+
+#[test]
+fn refuses_cut_operand() {
+    let mut code = asm(&[(OpCode::Const, &[0])]);
+    code.bytecode.pop();
+
+    assert_eq!(verify(&code).unwrap_err().err, InvalidCode::Incompleta);
+}
+
+#[test]
+fn refuses_missing_constant() {
+    let err = fails(&[
+        (OpCode::Const, &[2]),
+        (OpCode::Pop, &[]),
+        (OpCode::Halt, &[]),
+    ]);
+    assert_eq!(err.err, InvalidCode::Constante { index: 2, len: 2 });
+}
+
+#[test]
+fn refuses_jump_inside_instruction() {
+    // lands on the operand of the const
+    let err = fails(&[
+        (OpCode::Jmp, &[1]),
+        (OpCode::Const, &[0]),
+        (OpCode::Halt, &[]),
+    ]);
+    assert_eq!(
+        err,
+        VerifyError {
+            offset: 0,
+            err: InvalidCode::Salto(4)
+        }
+    );
+
+    let err = fails(&[(OpCode::Jmp, &[100]), (OpCode::Halt, &[])]);
+    assert_eq!(err.err, InvalidCode::Salto(103));
+}
+
+#[test]
+fn refuses_call_outside_function() {
+    let err = fails(&[(OpCode::Call, &[3, 0]), (OpCode::Halt, &[])]);
+    assert_eq!(err.err, InvalidCode::Chamada(3));
+
+    let err = fails(&[(OpCode::Call, &[0, 0]), (OpCode::Halt, &[])]);
+    assert_eq!(err.err, InvalidCode::Chamada(0));
+}
+
+#[test]
+fn refuses_pop_of_empty_stack() {
+    let err = fails(&[
+        (OpCode::Const, &[0]),
+        (OpCode::Add, &[]),
+        (OpCode::Halt, &[]),
+    ]);
+    assert_eq!(
+        err,
+        VerifyError {
+            offset: 3,
+            err: InvalidCode::PilhaVazia {
+                depth: 1,
+                needed: 2
+            }
+        }
+    );
+
+    let err = fails(&[(OpCode::List, &[3]), (OpCode::Halt, &[])]);
+    assert_eq!(
+        err.err,
+        InvalidCode::PilhaVazia {
+            depth: 0,
+            needed: 3
+        }
+    );
+}
+
+#[test]
+fn refuses_paths_with_different_depths() {
+    // the false branch pushes a value before meeting the other one at halt
+    let err = fails(&[
+        (OpCode::Const, &[1]),
+        (OpCode::JmpT, &[3]),
+        (OpCode::Const, &[0]),
+        (OpCode::Halt, &[]),
+    ]);
+    assert_eq!(
+        err.err,
+        InvalidCode::PilhaInconsistente {
+            depth: 1,
+            expected: 0
+        }
+    );
+
+    // a loop that grows the stack at every turn
+    let err = fails(&[(OpCode::Const, &[0]), (OpCode::Jmp, &[-6])]);
+    assert_eq!(
+        err.err,
+        InvalidCode::PilhaInconsistente {
+            depth: 1,
+            expected: 0
+        }
+    );
+}
+
+#[test]
+fn refuses_unallocated_variable() {
+    let err = fails(&[
+        (OpCode::Load, &[0]),
+        (OpCode::Pop, &[]),
+        (OpCode::Halt, &[]),
+    ]);
+    assert_eq!(err.err, InvalidCode::Variavel { slot: 0, size: 0 });
+
+    let err = fails(&[
+        (OpCode::Alloc, &[1]),
+        (OpCode::Const, &[0]),
+        (OpCode::Store, &[1]),
+        (OpCode::Halt, &[]),
+    ]);
+    assert_eq!(err.err, InvalidCode::Variavel { slot: 1, size: 1 });
+}
+
+#[test]
+fn refuses_bad_returns() {
+    let err = fails(&[(OpCode::ReturnV, &[])]);
+    assert_eq!(err.err, InvalidCode::RetornoForaDeFuncao);
+
+    // the function returns a value only when its argument is true
+    let err = fails(&[
+        (OpCode::Const, &[1]),
+        (OpCode::Call, &[10, 1]),
+        (OpCode::Halt, &[]),
+        (OpCode::Load, &[0]),
+        (OpCode::JmpF, &[4]),
+        (OpCode::Const, &[0]),
+        (OpCode::Return, &[]),
+        (OpCode::ReturnV, &[]),
+    ]);
+    assert_eq!(
+        err,
+        VerifyError {
+            offset: 19,
+            err: InvalidCode::RetornoMisto
+        }
+    );
+}
+
+#[test]
+fn refuses_calls_with_other_argument_count() {
+    let err = fails(&[
+        (OpCode::Const, &[0]),
+        (OpCode::Call, &[16, 1]),
+        (OpCode::Call, &[16, 0]),
+        (OpCode::Halt, &[]),
+        (OpCode::ReturnV, &[]),
+    ]);
+    assert_eq!(
+        err.err,
+        InvalidCode::Argumentos {
+            argc: 0,
+            expected: 1
+        }
+    );
+}
+
+#[test]
+fn refuses_code_without_end() {
+    let err = fails(&[(OpCode::Const, &[0]), (OpCode::Write, &[])]);
+    assert_eq!(
+        err,
+        VerifyError {
+            offset: 3,
+            err: InvalidCode::SemFim
+        }
+    );
+
+    assert_eq!(verify(&asm(&[])).unwrap_err().err, InvalidCode::SemFim);
+}
+
+#[test]
+fn reads_wide_operands() {
+    let mut code = asm(&[(OpCode::Alloc, &[300]), (OpCode::Const, &[0])]);
+    code.bytecode
+        .extend([OpCode::Wide as u8, OpCode::Store as u8]);
+    code.bytecode.extend(299u32.to_le_bytes());
+    code.bytecode.push(OpCode::Halt as u8);
+    assert_eq!(verify(&code), Ok(()));
+
+    // a wide jump back to the const, 15 bytes before its end
+    code.bytecode.pop();
+    code.bytecode
+        .extend([OpCode::Wide as u8, OpCode::Jmp as u8]);
+    code.bytecode.extend((-15i32).to_le_bytes());
+    assert_eq!(verify(&code), Ok(()));
+
+    let end = code.bytecode.len();
+    code.bytecode[end - 4..].copy_from_slice(&(-14i32).to_le_bytes());
+    assert_eq!(verify(&code).unwrap_err().err, InvalidCode::Salto(6));
+
+    let code = asm(&[(OpCode::Wide, &[]), (OpCode::Pop, &[]), (OpCode::Halt, &[])]);
+    assert_eq!(verify(&code).unwrap_err().err, InvalidCode::Prefixo);
+}
+
+#[test]
+fn vm_refuses_invalid_code() {
+    let code = asm(&[(OpCode::Pop, &[]), (OpCode::Halt, &[])]);
+    assert!(LinaVm::new(code, &[][..], Vec::new()).is_err());
+}
+
+#[test]
+fn corrupted_bytes_never_panic() {
+    let code = build(PROGRAMA);
+
+    for i in 0..code.bytecode.len() {
+        for byte in [0x00, 0x01, 0x0F, 0x30, 0x7F, 0xFF] {
+            let mut bytecode = code.bytecode.clone();
+            bytecode[i] = byte;
+            let corrupted = ByteCode {
+                bytecode,
+                constants: code.constants.clone(),
+                lines: Vec::new(),
+            };
+            _ = verify(&corrupted);
+        }
+    }
+
+    // a frame too big to allocate
+    assert_eq!(code.bytecode[0], OpCode::Alloc as u8);
+    let mut bytecode = code.bytecode.clone();
+    bytecode[1..1 + ADDR_SIZE].copy_from_slice(&u32::MAX.to_le_bytes());
+    let corrupted = ByteCode {
+        bytecode,
+        constants: code.constants.clone(),
+        lines: Vec::new(),
+    };
+    assert_eq!(
+        verify(&corrupted),
+        Err(VerifyError {
+            offset: 0,
+            err: InvalidCode::Alocacao(u32::MAX as usize),
+        })
+    );
+}
+
+#[test]
+fn branches_that_return_end_a_function() {
+    let code = build(
+        r#"
+        programa Teste
+
+        função sinal(inteiro n): inteiro
+            se n < 0 entao
+                retorne -1
+            senao se n = 0 entao
+                retorne 0
+            senao
+                retorne 1
+            fim
+        fim
+
+        saida(sinal(-5), sinal(0), sinal(5))
+    "#,
+    );
+    assert_eq!(verify(&code), Ok(()));
+}
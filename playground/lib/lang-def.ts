@@ -3,6 +3,7 @@ import type { languages, editor } from 'monaco-editor';
 export const CONFIG: languages.LanguageConfiguration = {
   comments: {
     lineComment: '#',
+    blockComment: ['/*', '*/'],
   },
   brackets: [
     ['{', '}'],
@@ -30,10 +31,10 @@ export const CONFIG: languages.LanguageConfiguration = {
   },
   indentationRules: {
     // não começa com um comentário
-    // tem as palavras senao, entao ou repetir
+    // tem as palavras senao, entao ou repetir, com ou sem acento
     // e não termina com fim (o que indicaria um one-liner)
     increaseIndentPattern:
-      /^((?!#).)*(\b(senao|entao|repetir)\b)(((?!\bfim\b).)*)(\s*)$/,
+      /^((?!#).)*(\b(senao|senão|entao|então|repetir)\b)(((?!\bfim\b).)*)(\s*)$/,
 
     // opcionalmente espaço vazio seguido pela palavra fim
     decreaseIndentPattern: /^\s*\bfim\b/,
@@ -44,21 +45,35 @@ export const GRAMMAR: languages.IMonarchLanguage = {
   // Set defaultToken to invalid to see what you do not tokenize yet
   // defaultToken: 'invalid',
 
-  keywords: ['programa', 'seja', 'verdadeiro', 'falso'],
+  keywords: [
+    'programa',
+    'seja',
+    'verdadeiro',
+    'falso',
+    'função',
+    'funcao',
+    'retorne',
+    'de',
+  ],
 
   controlFlowKeywords: [
     'para',
     'ate',
+    'até',
     'repetir',
     'incremento',
+    'pare',
+    'continue',
     'se',
     'entao',
+    'então',
     'senao',
+    'senão',
     'enquanto',
     'fim',
   ],
 
-  typeKeywords: ['booleano', 'real', 'inteiro', 'texto'],
+  typeKeywords: ['booleano', 'real', 'inteiro', 'texto', 'lista'],
 
   operators: [
     ':=',
@@ -70,6 +85,8 @@ export const GRAMMAR: languages.IMonarchLanguage = {
     '<>',
     'e',
     'ou',
+    'nao',
+    'não',
     '+',
     '-',
     '*',
@@ -88,16 +105,15 @@ export const GRAMMAR: languages.IMonarchLanguage = {
   // we include these common regular expressions
   symbols: /[=><!~?:&|+\-*\/\^%]+/,
 
-  // C# style strings
-  escapes:
-    /\\(?:[abfnrtv\\"']|x[0-9A-Fa-f]{1,4}|u[0-9A-Fa-f]{4}|U[0-9A-Fa-f]{8})/,
+  // \n, \t, \", \\ and \u{...}
+  escapes: /\\(?:[nt"\\]|u\{[0-9A-Fa-f]{1,6}\})/,
 
   // The main tokenizer for our languages
   tokenizer: {
     root: [
       // identifiers and keywords
       [
-        /[a-z_$][\w$]*/,
+        /[a-z_$\u00C0-\u024F][\w$\u00C0-\u024F]*/,
         {
           cases: {
             '@typeKeywords': 'type.identifier',
@@ -126,15 +142,16 @@ export const GRAMMAR: languages.IMonarchLanguage = {
       ],
 
       // numbers
-      [/\d*\.\d+([eE][\-+]?\d+)?/, 'number.float'],
-      [/0[xX][0-9a-fA-F]+/, 'number.hex'],
-      [/\d+/, 'number'],
+      [/\d[\d_]*\.\d[\d_]*([eE][\-+]?\d[\d_]*)?/, 'number.float'],
+      [/\d[\d_]*[eE][\-+]?\d[\d_]*/, 'number.float'],
+      [/0[xX][0-9a-fA-F_]+/, 'number.hex'],
+      [/0[bB][01_]+/, 'number.binary'],
+      [/\d[\d_]*/, 'number'],
 
       // delimiter: after number because of .\d floats
       [/[;,.]/, 'delimiter'],
 
       // strings
-      [/"([^"\\]|\\.)*$/, 'string.invalid'], // non-teminated string
       [/"/, { token: 'string.quote', bracket: '@open', next: '@string' }],
 
       // characters
@@ -160,7 +177,7 @@ export const GRAMMAR: languages.IMonarchLanguage = {
     whitespace: [
       [/[ \t\r\n]+/, 'white'],
       [/\/\*/, 'comment', '@comment'],
-      [/\/\/.*$/, 'comment'],
+      [/#.*$/, 'comment'],
     ],
   },
 };